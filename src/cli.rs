@@ -0,0 +1,742 @@
+use chrono::{Days, Local, NaiveDate};
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fs,
+    io::{self, BufRead, BufReader, IsTerminal, Write},
+    net::{TcpListener, TcpStream},
+    os::unix::{fs::FileTypeExt, net::UnixListener},
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    config::Config,
+    database::{
+        self, delete_completed_before, escalate_overdue, fetch_attachments, fetch_changes, fetch_fields, fetch_lists,
+        fetch_milestones, fetch_relations, fetch_todo, fetch_todos, remove_field, set_field, toggle_todo_completion,
+        update_todo,
+    },
+    list_template,
+    model::Todo,
+    plugin::Plugins,
+    query::{Order, TodoQuery},
+    quickadd::{QuickAdd, DEFAULT_LIST},
+    serialize::{json_members, json_object, json_string, serialize, Format},
+    stats::{list_aging, Aging, AGE_BUCKETS},
+    suggest::Classifier,
+    template,
+};
+
+/// Prints every incomplete todo as `id<TAB>list<TAB>due<TAB>title`. The id
+/// comes first so a selected line can be fed straight back into `done --from-line`.
+pub fn pick() -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    for list in fetch_lists()? {
+        let mut todos = fetch_todos(&TodoQuery::new().list(list.id.expect("Id exists")).completed(false))?;
+        todos.sort_by_key(|t| t.due_date.is_none());
+        for todo in todos {
+            let written = writeln!(
+                out,
+                "{}\t{}\t{}\t{}",
+                todo.id.expect("Id exists"),
+                list.title,
+                todo.due_date.map(|d| d.to_string()).unwrap_or_default(),
+                todo.title
+            );
+            // The picker usually closes the pipe as soon as a line is chosen.
+            if let Err(e) = written {
+                return match e.kind() {
+                    io::ErrorKind::BrokenPipe => Ok(()),
+                    _ => Err(e.into()),
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the `limit` most urgent incomplete todos as
+/// `id<TAB>list<TAB>urgency<TAB>title`, see `plugin::Plugins::urgency`.
+pub fn next(limit: usize) -> Result<(), Box<dyn Error>> {
+    let plugins = Plugins::load();
+    plugins.errors.iter().for_each(|e| eprintln!("{}", e));
+    let today = Local::now().date_naive();
+    let lists = fetch_lists()?;
+    let mut scored = vec![];
+    for todo in fetch_todos(&TodoQuery::new().completed(false))? {
+        scored.push((plugins.urgency(&todo, today)?, todo));
+    }
+    // Ties keep the order the todos were added in.
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    for (urgency, todo) in scored.iter().take(limit) {
+        let list = lists.iter().find(|l| l.id == Some(todo.list_id)).map_or("", |l| l.title.as_str());
+        println!("{}\t{}\t{}\t{}", todo.id.expect("Id exists"), list, urgency, todo.title);
+    }
+    Ok(())
+}
+
+pub fn done(mut ids: Vec<usize>, from_line: bool) -> Result<(), Box<dyn Error>> {
+    if from_line {
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            match line.split('\t').next().and_then(|id| id.trim().parse::<usize>().ok()) {
+                Some(id) => ids.push(id),
+                None if line.trim().is_empty() => {}
+                None => eprintln!("Could not read a todo id from: {}", line),
+            }
+        }
+    }
+    // The known todos are completed even when some ids are wrong.
+    let mut unknown = vec![];
+    for id in ids {
+        if fetch_todo(id)?.is_none() {
+            unknown.push(id.to_string());
+            continue;
+        }
+        toggle_todo_completion(id, true)?;
+        println!("Completed {}", id);
+    }
+    match unknown.is_empty() {
+        true => Ok(()),
+        false => Err(format!("No todo with id {}", unknown.join(", ")).into()),
+    }
+}
+
+/// Sets and removes custom fields of a todo, then prints the fields it ends
+/// up with.
+pub fn edit(id: usize, set: Vec<(String, String)>, unset: Vec<String>) -> Result<(), Box<dyn Error>> {
+    if fetch_todo(id)?.is_none() {
+        return Err(format!("No todo with id {}", id).into());
+    }
+    for (key, value) in &set {
+        set_field(id, key, value)?;
+    }
+    for key in &unset {
+        remove_field(id, key)?;
+    }
+    for (key, value) in fetch_fields(id)? {
+        println!("{}={}", key, value);
+    }
+    Ok(())
+}
+
+/// Prints everything stored about one todo, paged when it doesn't fit the
+/// terminal.
+pub fn show(id: usize, json: bool) -> Result<(), Box<dyn Error>> {
+    let Some(todo) = fetch_todo(id)? else {
+        return Err(format!("No todo with id {}", id).into());
+    };
+    let list = fetch_lists()?.into_iter().find(|l| l.id == Some(todo.list_id));
+    let milestone = fetch_milestones(todo.list_id)?
+        .into_iter()
+        .find(|m| m.id.is_some() && m.id == todo.milestone_id);
+    let fields = fetch_fields(id)?;
+    let attachments = fetch_attachments(id)?;
+    let mut links = vec![];
+    for relation in fetch_relations(id)? {
+        if let Some(related) = fetch_todo(relation.related_id)? {
+            links.push((relation.kind, related));
+        }
+    }
+
+    if json {
+        let mut members = json_members(&todo);
+        members.push(("list", list.map_or("null".to_string(), |l| json_string(&l.title))));
+        members.push(("milestone", milestone.map_or("null".to_string(), |m| json_string(&m.title))));
+        let fields: Vec<(&str, String)> = fields.iter().map(|(key, value)| (key.as_str(), json_string(value))).collect();
+        members.push(("fields", json_object(&fields)));
+        let attachments: Vec<String> = attachments.iter().map(|url| json_string(url)).collect();
+        members.push(("attachments", format!("[{}]", attachments.join(", "))));
+        let links: Vec<String> = links
+            .iter()
+            .map(|(kind, related)| {
+                json_object(&[
+                    ("relation", json_string(kind)),
+                    ("id", related.id.map_or("null".to_string(), |id| id.to_string())),
+                    ("title", json_string(&related.title)),
+                    ("completed", related.completed.to_string()),
+                ])
+            })
+            .collect();
+        members.push(("links", format!("[{}]", links.join(", "))));
+        println!("{}", json_object(&members));
+        return Ok(());
+    }
+
+    let mut out = vec![format!("{} [{}] {}", id, if todo.completed { "x" } else { " " }, todo.title)];
+    out.extend(list.map(|l| format!("List:      {}", l.title)));
+    out.extend(milestone.map(|m| format!("Milestone: {}", m.title)));
+    out.extend(todo.due_date.map(|d| format!("Due:       {}", d.format("%A %Y-%m-%d"))));
+    out.extend(todo.slot.map(|s| format!("Slot:      {}", s)));
+    out.push(format!("Priority:  {}", todo.priority));
+    out.extend(todo.created_date.map(|d| format!("Created:   {}", d)));
+    out.extend(todo.completed_at.filter(|_| todo.completed).map(|d| format!("Completed: {}", d.format("%Y-%m-%d %H:%M"))));
+    if let Some(description) = todo.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        out.push("".to_string());
+        out.push("Description".to_string());
+        out.extend(description.lines().map(|line| format!("  {}", line)));
+    }
+    if !fields.is_empty() {
+        out.push("".to_string());
+        out.push("Fields".to_string());
+        out.extend(fields.iter().map(|(key, value)| format!("  {}: {}", key, value)));
+    }
+    if !attachments.is_empty() {
+        out.push("".to_string());
+        out.push("Attachments".to_string());
+        out.extend(attachments.iter().map(|url| format!("  {}", url)));
+    }
+    if !links.is_empty() {
+        out.push("".to_string());
+        out.push("Links".to_string());
+        out.extend(links.iter().map(|(kind, related)| {
+            let mark = if related.completed { "x" } else { " " };
+            format!("  {}: {} [{}] {}", kind, related.id.unwrap_or_default(), mark, related.title)
+        }));
+    }
+    page(&out.join("\n"))
+}
+
+/// Prints how long incomplete todos have been around, per list, as a table
+/// followed by the `oldest` oldest todos of each list, or as JSON.
+pub fn aging(oldest: usize, json: bool) -> Result<(), Box<dyn Error>> {
+    let today = Local::now().date_naive();
+    let lists = fetch_lists()?;
+    let todos: Vec<Vec<Todo>> = lists
+        .iter()
+        .map(|list| fetch_todos(&TodoQuery::new().list(list.id.expect("Id exists")).completed(false)))
+        .collect::<Result<_, _>>()?;
+    let reports: Vec<Aging> = lists.iter().zip(&todos).map(|(list, todos)| list_aging(list, todos, today, oldest)).collect();
+
+    if json {
+        let lists: Vec<String> = reports
+            .iter()
+            .map(|report| {
+                let buckets: Vec<(&str, String)> =
+                    AGE_BUCKETS.iter().zip(report.buckets).map(|((label, _), count)| (*label, count.to_string())).collect();
+                let oldest: Vec<String> = report
+                    .oldest
+                    .iter()
+                    .map(|(todo, age)| {
+                        json_object(&[
+                            ("id", todo.id.map_or("null".to_string(), |id| id.to_string())),
+                            ("title", json_string(&todo.title)),
+                            ("age_days", age.to_string()),
+                        ])
+                    })
+                    .collect();
+                json_object(&[
+                    ("list", json_string(&report.title)),
+                    ("buckets", json_object(&buckets)),
+                    ("undated", report.undated.to_string()),
+                    ("oldest", format!("[{}]", oldest.join(", "))),
+                ])
+            })
+            .collect();
+        println!("{}", json_object(&[("lists", format!("[{}]", lists.join(", ")))]));
+        return Ok(());
+    }
+
+    let mut rows = vec![];
+    let mut header = vec!["List".to_string()];
+    header.extend(AGE_BUCKETS.iter().map(|(label, _)| label.to_string()));
+    header.push("undated".to_string());
+    rows.push(header);
+    for report in &reports {
+        let mut row = vec![report.title.clone()];
+        row.extend(report.buckets.iter().map(|count| count.to_string()));
+        row.push(report.undated.to_string());
+        rows.push(row);
+    }
+    let mut widths = vec![0; rows[0].len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = io::stdout().lock();
+    for row in &rows {
+        // The list column is aligned left, the counts right.
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| match column {
+                0 => format!("{:<1$}", cell, width),
+                _ => format!("{:>1$}", cell, width),
+            })
+            .collect();
+        writeln!(out, "{}", cells.join("  "))?;
+    }
+    for report in reports.iter().filter(|report| !report.oldest.is_empty()) {
+        writeln!(out)?;
+        writeln!(out, "Oldest in {}", report.title)?;
+        for (todo, age) in &report.oldest {
+            writeln!(out, "  {:>5}  {:>4}  {}", format!("{}d", age), todo.id.unwrap_or(0), todo.title)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn install_template(source: &str, list: Option<String>) -> Result<(), Box<dyn Error>> {
+    let template = list_template::parse(&list_template::read(source)?)?;
+    let (title, added) = list_template::install(template, list)?;
+    println!("Created {} with {} todos", title, added);
+    Ok(())
+}
+
+/// Deletes the todos completed more than `days` days ago.
+pub fn tidy(days: u64) -> Result<(), Box<dyn Error>> {
+    let deleted = delete_completed_before(Local::now().date_naive() - Days::new(days))?;
+    println!("Deleted {} todos completed more than {} days ago", deleted, days);
+    Ok(())
+}
+
+/// Copies the database to the backups directory, keeping the newest `keep`.
+pub fn backup(keep: usize) -> Result<(), Box<dyn Error>> {
+    let path = database::backup(keep)?;
+    println!("Backed up to {}", path.display());
+    Ok(())
+}
+
+/// Prints `text`, through `$PAGER` (or less) when stdout is a terminal too
+/// short to show all of it.
+fn page(text: &str) -> Result<(), Box<dyn Error>> {
+    let height = crossterm::terminal::size().map_or(usize::MAX, |(_, rows)| rows as usize);
+    if !io::stdout().is_terminal() || text.lines().count() < height {
+        println!("{}", text);
+        return Ok(());
+    }
+    let pager = env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+    let mut words = pager.split_whitespace();
+    let Some(program) = words.next() else {
+        println!("{}", text);
+        return Ok(());
+    };
+    let mut child = Command::new(program).args(words).stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may be quit before reading everything.
+        writeln!(stdin, "{}", text).ok();
+    }
+    child.wait()?;
+    Ok(())
+}
+
+pub fn escalate(after: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let Some(days) = after.or(Config::load().escalate_after) else {
+        return Err("No number of days given, pass --after or set escalate_after in the config".into());
+    };
+    let raised = escalate_overdue(days, Local::now().date_naive())?;
+    println!("Raised the priority of {} todos", raised);
+    Ok(())
+}
+
+/// Walks through the incomplete todos due today or earlier, oldest first.
+/// Reads whole lines rather than single keys and never takes over the
+/// screen, so it keeps working over a slow or flaky ssh connection.
+pub fn triage() -> Result<(), Box<dyn Error>> {
+    let config = Config::load();
+    let today = Local::now().date_naive();
+    let lists = fetch_lists()?;
+    let todos = fetch_todos(&TodoQuery::new().completed(false).due_to(today).order(Order::Due))?;
+    if todos.is_empty() {
+        println!("Nothing due, all clear");
+        return Ok(());
+    }
+
+    let (mut done, mut snoozed) = (0, 0);
+    let mut input = io::stdin().lock().lines();
+    'todos: for (index, todo) in todos.iter().enumerate() {
+        let list = lists.iter().find(|l| l.id == Some(todo.list_id)).map_or("", |l| l.title.as_str());
+        let due = todo.due_date.expect("Only todos with a due date are fetched");
+        let overdue = match (today - due).num_days() {
+            0 => "due today".to_string(),
+            1 => "1 day overdue".to_string(),
+            days => format!("{} days overdue", days),
+        };
+        println!();
+        println!("[{}/{}] {} ({}, {})", index + 1, todos.len(), todo.title, list, overdue);
+        if let Some(description) = todo.description.as_deref().and_then(|d| d.lines().next()).filter(|l| !l.is_empty()) {
+            println!("  {}", description);
+        }
+        loop {
+            print!("d(one) s(nooze) k(eep) q(uit)? ");
+            io::stdout().flush()?;
+            let Some(line) = input.next() else {
+                println!();
+                break 'todos;
+            };
+            match line?.trim() {
+                "d" | "done" => {
+                    toggle_todo_completion(todo.id.expect("Id exists"), true)?;
+                    done += 1;
+                }
+                "s" | "snooze" => {
+                    let mut snoozed_todo = todo.clone();
+                    snoozed_todo.due_date = config.due_in(today, 1);
+                    update_todo(&snoozed_todo)?;
+                    snoozed += 1;
+                    println!("Snoozed to {}", snoozed_todo.due_date.map(|d| d.to_string()).unwrap_or_default());
+                }
+                "k" | "keep" | "" => {}
+                "q" | "quit" => break 'todos,
+                _ => continue,
+            }
+            break;
+        }
+    }
+    println!("{} done, {} snoozed", done, snoozed);
+    Ok(())
+}
+
+/// Adds the todo described by `text`, or with `from_stdin` one todo per
+/// line read from stdin, both in quick-add syntax. Todos that don't name a
+/// list go into `list`, or with `suggest` the list their words fit best, see
+/// `suggest::Classifier`. Returns the title of the list the last todo went
+/// into.
+pub fn add(
+    text: Vec<String>,
+    list: Option<String>,
+    from_stdin: bool,
+    suggest: bool,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let config = Config::load();
+    let classifier = match suggest && list.is_none() {
+        true => Some(Classifier::from_database()?),
+        false => None,
+    };
+    let lines = match from_stdin {
+        true => io::stdin().lock().lines().collect::<Result<Vec<_>, _>>()?,
+        false => vec![text.join(" ")],
+    };
+    let mut added_to = None;
+    for line in &lines {
+        let line = match from_stdin {
+            true => strip_bullet(line),
+            false => line,
+        };
+        if from_stdin && line.is_empty() {
+            continue;
+        }
+        let mut todo = QuickAdd::parse(line, &config);
+        let mut suggested = false;
+        if let Some(list) = &list {
+            todo = todo.or_list(list, &config);
+        } else if let Some(list) = classifier.as_ref().filter(|_| todo.list.is_none()).and_then(|c| c.suggest(&todo.title)) {
+            todo = todo.or_list(list, &config);
+            suggested = true;
+        }
+        let title = todo.list_title().to_string();
+        let id = todo.save()?;
+        match suggested {
+            true => println!("Added {} to {}, pass --no-suggest to keep it in the {}", id, title, DEFAULT_LIST),
+            false => println!("Added {}", id),
+        }
+        added_to = Some(title);
+    }
+    Ok(added_to)
+}
+
+/// Notes pasted from elsewhere often come as a bulleted list or checklist,
+/// e.g. `- [ ] Send the slides`.
+fn strip_bullet(line: &str) -> &str {
+    let line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+    line.strip_prefix("[ ]").unwrap_or(line).trim_start()
+}
+
+/// Creates a todo for every line written to the socket, answering each line
+/// with `added <id>` or `error <message>`.
+pub fn listen(socket: PathBuf) -> Result<(), Box<dyn Error>> {
+    // A socket left over from an earlier run is replaced, anything else at
+    // the path is left alone.
+    match fs::symlink_metadata(&socket) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(&socket)?,
+        Ok(_) => return Err(format!("{} exists and is not a socket", socket.display()).into()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let listener = UnixListener::bind(&socket)?;
+    println!("Listening on {}", socket.display());
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Connection failed: {}", e);
+                continue;
+            }
+        };
+        thread::spawn(move || {
+            let config = Config::load();
+            let mut writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(_) => return,
+            };
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let reply = match QuickAdd::parse(&line, &config).save() {
+                    Ok(id) => format!("added {}", id),
+                    Err(e) => format!("error {}", e),
+                };
+                if writeln!(writer, "{}", reply).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Prints what changed after `since` as a JSON object, so a copy of the
+/// todos elsewhere can be kept current without transferring all of them.
+pub fn changes(since: i64) -> Result<(), Box<dyn Error>> {
+    let delta = fetch_changes(since)?;
+    let todos: Vec<String> = delta.todos.iter().map(|todo| json_object(&json_members(todo))).collect();
+    let deleted: Vec<String> = delta.deleted.iter().map(|id| id.to_string()).collect();
+    println!(
+        "{}",
+        json_object(&[
+            ("cursor", delta.cursor.to_string()),
+            ("todos", format!("[{}]", todos.join(", "))),
+            ("deleted", format!("[{}]", deleted.join(", "))),
+        ])
+    );
+    Ok(())
+}
+
+/// Seconds between reloads of a published page.
+const PUBLISH_REFRESH: u64 = 30;
+
+/// Serves the list titled `list` as an HTML page on `bind`, read fresh from
+/// the database on every request. Nothing can be changed through it.
+pub fn publish(list: String, bind: String, port: u16) -> Result<(), Box<dyn Error>> {
+    if !fetch_lists()?.iter().any(|l| l.title == list) {
+        return Err(format!("No list named {}", list).into());
+    }
+    let listener = TcpListener::bind((bind.as_str(), port))?;
+    println!("Publishing {} on {}:{}", list, bind, port);
+    for stream in listener.incoming() {
+        match stream {
+            // A slow client only holds up its own thread.
+            Ok(stream) => {
+                let list = list.clone();
+                thread::spawn(move || {
+                    if let Err(e) = respond(stream, &list) {
+                        eprintln!("Request failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => eprintln!("Connection failed: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// How long a client may take to send its request or read the page.
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn respond(stream: TcpStream, list: &str) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(PUBLISH_TIMEOUT))?;
+    stream.set_write_timeout(Some(PUBLISH_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+    let request = lines.next().transpose()?.unwrap_or_default();
+    // Read past the headers, the page doesn't depend on them.
+    for line in lines.by_ref() {
+        if line?.is_empty() {
+            break;
+        }
+    }
+    let (status, body) = match request.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+        ["GET", "/"] => ("200 OK", published_page(list)?),
+        ["GET", _] => ("404 Not Found", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Read only\n".to_string()),
+    };
+    let content_type = if status.starts_with("200") { "text/html" } else { "text/plain" };
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn published_page(title: &str) -> Result<String, Box<dyn Error>> {
+    let mut items = String::new();
+    match fetch_lists()?.into_iter().find(|l| l.title == title) {
+        Some(list) => {
+            let mut todos = fetch_todos(&TodoQuery::new().list(list.id.expect("Id exists")))?;
+            // Open todos first, the most pressing on top.
+            todos.sort_by_key(|t| (t.completed, std::cmp::Reverse(t.priority), t.due_date.is_none(), t.due_date));
+            for todo in &todos {
+                let due = todo.due_date.map_or(String::new(), |d| format!(" <small>{}</small>", d));
+                let title = format!("{}{}{}", todo.priority.marker(), escape_html(&todo.title), due);
+                match todo.completed {
+                    true => items.push_str(&format!("<li><s>{}</s></li>\n", title)),
+                    false => items.push_str(&format!("<li>{}</li>\n", title)),
+                }
+            }
+            if todos.is_empty() {
+                items.push_str("<li>Nothing here</li>\n");
+            }
+        }
+        None => items.push_str("<li>The list is gone</li>\n"),
+    }
+    Ok(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta http-equiv=\"refresh\" content=\"{}\">\n<title>{}</title>\n\
+         <style>body {{ font-family: sans-serif; }} s {{ color: gray; }}</style>\n</head>\n\
+         <body>\n<h1>{}</h1>\n<ul>\n{}</ul>\n<p><small>Updated {}</small></p>\n</body>\n</html>\n",
+        PUBLISH_REFRESH,
+        escape_html(title),
+        escape_html(title),
+        items,
+        Local::now().format("%Y-%m-%d %H:%M")
+    ))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Prints all todos, or those of the list titled `list`, in `format`.
+pub fn export(format: Format, list: Option<String>) -> Result<(), Box<dyn Error>> {
+    let mut query = TodoQuery::new();
+    if let Some(name) = &list {
+        match fetch_lists()?.into_iter().find(|l| &l.title == name) {
+            Some(list) => query = query.list(list.id.expect("Id exists")),
+            None => return Err(format!("No list named {}", name).into()),
+        }
+    }
+    print!("{}", serialize(format, &fetch_todos(&query)?));
+    Ok(())
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum GroupBy {
+    List,
+}
+
+/// Prints incomplete todos due on or before `date` as aligned columns,
+/// optionally with their tags and descriptions and grouped by list.
+pub fn due(date: NaiveDate, long: bool, group_by: Option<GroupBy>) -> Result<(), Box<dyn Error>> {
+    let lists = fetch_lists()?;
+    let todos = fetch_todos(&TodoQuery::new().completed(false).due_to(date).order(Order::Due))?;
+    let list_title = |todo: &Todo| {
+        lists
+            .iter()
+            .find(|l| l.id == Some(todo.list_id))
+            .map(|l| l.title.clone())
+            .unwrap_or_default()
+    };
+
+    let groups: Vec<(Option<String>, Vec<&Todo>)> = match group_by {
+        None => vec![(None, todos.iter().collect())],
+        Some(GroupBy::List) => lists
+            .iter()
+            .map(|list| (Some(list.title.clone()), todos.iter().filter(|t| t.list_id == list.id.unwrap_or(0)).collect()))
+            .filter(|(_, todos): &(_, Vec<_>)| !todos.is_empty())
+            .collect(),
+    };
+
+    // Columns before the title, then the title and the tags.
+    let leading = |todo: &Todo| {
+        let mut cells = vec![
+            todo.id.unwrap_or(0).to_string(),
+            todo.due_date.map(|d| d.to_string()).unwrap_or_default(),
+        ];
+        if group_by.is_none() {
+            cells.push(list_title(todo));
+        }
+        cells
+    };
+    let row = |todo: &Todo| {
+        let mut cells = leading(todo);
+        cells.push(todo.title.clone());
+        if long {
+            cells.push(todo.tags().iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" "));
+        }
+        cells
+    };
+    // Every group is aligned the same way, so the columns line up across
+    // headings too.
+    let mut widths = vec![];
+    for cells in todos.iter().map(row) {
+        widths.resize(cells.len(), 0);
+        for (width, cell) in widths.iter_mut().zip(&cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let indent: usize = todos.first().map_or(0, |todo| widths.iter().take(leading(todo).len()).map(|w| w + 2).sum());
+
+    let mut out = io::stdout().lock();
+    for (index, (heading, group)) in groups.iter().enumerate() {
+        if let Some(heading) = heading {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{} ({})", heading, group.len())?;
+        }
+        for todo in group {
+            let line: Vec<String> = row(todo).iter().zip(&widths).map(|(cell, width)| format!("{:<1$}", cell, width)).collect();
+            writeln!(out, "{}", line.join("  ").trim_end())?;
+            if long {
+                for description in todo.description.iter().flat_map(|d| d.lines()).filter(|l| !l.trim().is_empty()) {
+                    writeln!(out, "{}{}", " ".repeat(indent), description)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub struct ListOptions {
+    pub list: Option<String>,
+    /// Which todos to print. Narrowed to `list` when one is given.
+    pub query: TodoQuery,
+    pub template: String,
+    pub header: Option<String>,
+    pub footer: Option<String>,
+}
+
+/// Prints todos through a user supplied template, see `template::render`.
+pub fn list(options: ListOptions) -> Result<(), Box<dyn Error>> {
+    let lists = fetch_lists()?;
+    let mut query = options.query;
+    if let Some(name) = &options.list {
+        match lists.iter().find(|l| &l.title == name) {
+            Some(list) => query = query.list(list.id.expect("Id exists")),
+            None => return Err(format!("No list named {}", name).into()),
+        }
+    }
+    let todos: Vec<_> = fetch_todos(&query)?
+        .into_iter()
+        .map(|todo| {
+            let list = lists.iter().find(|l| l.id == Some(todo.list_id));
+            (todo, list)
+        })
+        .collect();
+
+    let count = HashMap::from([("count", todos.len().to_string())]);
+    let mut out = String::new();
+    if let Some(header) = &options.header {
+        out.push_str(&template::render(header, &count)?);
+    }
+    for (todo, list) in &todos {
+        out.push_str(&template::render(&options.template, &template::todo_fields(todo, *list))?);
+    }
+    if let Some(footer) = &options.footer {
+        out.push_str(&template::render(footer, &count)?);
+    }
+    print!("{}", out);
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use std::{collections::HashMap, env, fs, io::Write, path::PathBuf};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use crate::{
+    database::todo_dir,
+    jobs::Job,
+    rules::{self, ColorRule, Predicate},
+};
+
+/// Settings read from `~/.todo/config`, one `key = value` per line. Lines
+/// starting with `#` are comments and unknown keys are ignored.
+///
+/// ```text
+/// skip_weekends = true
+/// holidays = 2026-12-24, 2026-12-25
+/// key.o = open-links
+/// color = tag:urgent -> bold red
+/// color = due:<2d -> yellow
+/// render = reduced
+/// escalate_after = 7
+/// workspace.acme = ~/clients/acme.sqlite
+/// pin = 4711
+/// lock_after = 10
+/// list_quota = 200
+/// todo_quota = 10000
+/// description.Bugs = Steps:\n- \nOutcome:
+/// plain = true
+/// reduced_motion = true
+/// job.backup = daily 02:00 backup --keep 14
+/// job.tidy = weekly sun 03:00 tidy --older-than 90
+/// job.digest = daily 08:00 ! todo report aging | mail -s Todos me
+/// ```
+#[derive(Debug, Default)]
+pub struct Config {
+    /// Move due dates that land on a Saturday or Sunday to the next Monday.
+    pub skip_weekends: bool,
+    /// Dates that due dates are moved past, like weekends.
+    pub holidays: Vec<NaiveDate>,
+    /// Keys in the list view bound to an executable plugin, from
+    /// `key.<char> = <plugin>`. Built-in keys and those bound by script
+    /// plugins take precedence.
+    pub key_actions: HashMap<char, String>,
+    /// `color = predicate -> style` lines, applied in order to list items.
+    /// See `rules::ColorRule`.
+    pub color_rules: Vec<ColorRule>,
+    /// `render = reduced|full`, see `render::set_reduced`. Unset or `auto`
+    /// picks reduced rendering inside an ssh session.
+    pub reduced_rendering: Option<bool>,
+    /// `escalate_after = N`: todos overdue by more than N days are raised to
+    /// high priority, and after 2N days to urgent. See
+    /// `database::escalate_overdue`.
+    pub escalate_after: Option<u64>,
+    /// `workspace.<name> = <path>`: other databases to switch to, in the
+    /// order they are configured. Relative paths are relative to `~/.todo`.
+    pub workspaces: Vec<(String, PathBuf)>,
+    /// `pin = ...`: asked for when the TUI starts and after `lock_after`.
+    /// It keeps passers-by on a shared machine out of the TUI, anyone who
+    /// can read this file or the database gets in regardless.
+    pub pin: Option<String>,
+    /// `lock_after = N`: lock the TUI after N minutes without input.
+    pub lock_after: Option<u64>,
+    /// `list_quota = N`: warn when a list has more than N incomplete todos.
+    pub list_quota: Option<usize>,
+    /// `todo_quota = N`: warn when the database holds more than N todos,
+    /// completed ones included.
+    pub todo_quota: Option<usize>,
+    /// `description.<list> = <template>`: the description new todos in the
+    /// list titled `<list>` start with. `\n` starts a new line.
+    pub description_templates: HashMap<String, String>,
+    /// `plain = true`: no colors and no bold, italic or underlined text.
+    /// What colors mark, like overdue todos, is written out instead.
+    pub plain: bool,
+    /// `reduced_motion = true`: only redraw after input or a change, never
+    /// just because time passed.
+    pub reduced_motion: bool,
+    /// `job.<name> = <schedule> <action>`: run by `todo daemon run`, see
+    /// `jobs::Job`.
+    pub jobs: Vec<Job>,
+}
+
+/// The name of the workspace using `~/.todo/todos.sqlite`.
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+impl Config {
+    pub fn load() -> Config {
+        match fs::read_to_string(todo_dir().join("config")) {
+            Ok(content) => Config::parse(&content),
+            Err(_) => Config::default(),
+        }
+    }
+
+    /// Adds lines to the end of the config file, creating it if needed.
+    pub fn append(lines: &[&str]) -> std::io::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(todo_dir().join("config"))?;
+        for line in lines {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the color rules for `#tag` in the config file with one
+    /// applying `style`. Without any color rules so far the default ones are
+    /// written too, as they stop applying once there are rules.
+    pub fn set_tag_color(&self, tag: &str, style: &str) -> std::io::Result<()> {
+        let path = todo_dir().join("config");
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let is_rule_for_tag = |line: &str| {
+            line.split_once('=')
+                .filter(|(key, _)| key.trim() == "color")
+                .and_then(|(_, rule)| ColorRule::parse(rule.trim()))
+                .is_some_and(|rule| rule.predicate == Predicate::Tag(tag.to_string()))
+        };
+        let mut lines: Vec<String> = content.lines().filter(|line| !is_rule_for_tag(line)).map(String::from).collect();
+        if self.color_rules.is_empty() {
+            lines.extend(rules::DEFAULT_RULES.iter().map(|rule| format!("color = {}", rule)));
+        }
+        lines.push(format!("color = tag:{} -> {}", tag, style));
+        fs::write(path, lines.join("\n") + "\n")
+    }
+
+    pub fn parse(content: &str) -> Config {
+        let mut config = Config::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "skip_weekends" => config.skip_weekends = value == "true",
+                "holidays" => {
+                    config.holidays = value
+                        .split(',')
+                        .filter_map(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").ok())
+                        .collect()
+                }
+                "color" => config.color_rules.extend(ColorRule::parse(value)),
+                "render" => {
+                    config.reduced_rendering = match value {
+                        "reduced" => Some(true),
+                        "full" => Some(false),
+                        _ => None,
+                    }
+                }
+                "escalate_after" => config.escalate_after = value.parse().ok().filter(|&days| days > 0),
+                "pin" => config.pin = Some(value.to_string()).filter(|pin| !pin.is_empty()),
+                "lock_after" => config.lock_after = value.parse().ok().filter(|&minutes| minutes > 0),
+                "list_quota" => config.list_quota = value.parse().ok().filter(|&todos| todos > 0),
+                "todo_quota" => config.todo_quota = value.parse().ok().filter(|&todos| todos > 0),
+                "plain" => config.plain = value == "true",
+                "reduced_motion" => config.reduced_motion = value == "true",
+                key if key.starts_with("workspace.") => {
+                    let name = key["workspace.".len()..].trim();
+                    if !name.is_empty() && name != DEFAULT_WORKSPACE {
+                        config.workspaces.push((name.to_string(), expand_path(value)));
+                    }
+                }
+                key if key.starts_with("job.") => {
+                    config.jobs.extend(Job::parse(key["job.".len()..].trim(), value));
+                }
+                key if key.starts_with("description.") => {
+                    let list = key["description.".len()..].trim();
+                    config.description_templates.insert(list.to_string(), value.replace("\\n", "\n"));
+                }
+                key => {
+                    let mut chars = key.strip_prefix("key.").unwrap_or_default().chars();
+                    if let (Some(c), None) = (chars.next(), chars.next()) {
+                        config.key_actions.insert(c, value.to_string());
+                    }
+                }
+            }
+        }
+        config
+    }
+
+    /// The description new todos in the list titled `list` start with.
+    pub fn description_template(&self, list: &str) -> Option<&str> {
+        self.description_templates.get(list).map(String::as_str)
+    }
+
+    /// The configured color rules, or the default of highlighting overdue
+    /// todos in red.
+    pub fn color_rules(&self) -> Vec<ColorRule> {
+        match self.color_rules.is_empty() {
+            true => rules::default_rules(),
+            false => self.color_rules.clone(),
+        }
+    }
+
+    pub fn reduced_rendering(&self) -> bool {
+        self.reduced_rendering
+            .unwrap_or_else(|| env::var_os("SSH_CONNECTION").is_some())
+    }
+
+    /// Whether to leave out colors: with `plain`, or when the `NO_COLOR`
+    /// environment variable is set to anything, see https://no-color.org.
+    pub fn no_color(&self) -> bool {
+        self.plain || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+    }
+
+    /// Every workspace with the path of its database, `None` for the default
+    /// one, which always comes first.
+    pub fn workspaces(&self) -> Vec<(String, Option<PathBuf>)> {
+        let configured = self.workspaces.iter().map(|(name, path)| (name.clone(), Some(path.clone())));
+        std::iter::once((DEFAULT_WORKSPACE.to_string(), None)).chain(configured).collect()
+    }
+
+    /// The database path of the workspace called `name`, see `workspaces`.
+    pub fn workspace(&self, name: &str) -> Option<Option<PathBuf>> {
+        self.workspaces()
+            .into_iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, path)| path)
+    }
+
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        let weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        if self.skip_weekends && weekend {
+            return false;
+        }
+        !self.holidays.contains(&date)
+    }
+
+    /// The first working day on or after `date`.
+    pub fn working_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut date = date;
+        // Bounded so a config that rules out every day can't hang the app.
+        for _ in 0..366 {
+            if self.is_working_day(date) {
+                break;
+            }
+            date = date + Days::new(1);
+        }
+        date
+    }
+
+    /// `days` after `from`, moved forward to a working day.
+    pub fn due_in(&self, from: NaiveDate, days: u64) -> Option<NaiveDate> {
+        from.checked_add_days(Days::new(days)).map(|date| self.working_day(date))
+    }
+}
+
+pub fn expand_path(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => env::var_os("HOME").map(PathBuf::from).unwrap_or_default().join(rest),
+        None => todo_dir().join(path),
+    }
+}
@@ -0,0 +1,96 @@
+use ratatui::widgets::ListState;
+
+/// The selected row of a list pane. Every access goes through the items it
+/// points into, so an empty collection, a deletion that shrinks it or a stale
+/// index can't cause an out of bounds panic.
+///
+/// The cursor also remembers the ids of the rows it was last restored
+/// against, so a refetch that re-sorts them keeps the same item selected
+/// rather than the same position.
+#[derive(Default)]
+pub struct Cursor {
+    state: ListState,
+    ids: Vec<usize>,
+    /// Rendering state for panes that show rows between the items, kept so
+    /// the scroll offset survives between frames.
+    rows: ListState,
+}
+
+impl Cursor {
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.state.select(index);
+    }
+
+    /// The selected item, if the selection points at one.
+    pub fn get<'a, T>(&self, items: &'a [T]) -> Option<&'a T> {
+        self.selected().and_then(|index| items.get(index))
+    }
+
+    /// Moves up among `len` rows, leaving the pane when moving past the
+    /// first one.
+    pub fn up(&mut self, len: usize) {
+        let index = match (self.selected(), len) {
+            (_, 0) | (Some(0), _) => None,
+            (Some(index), len) => Some((index - 1).min(len - 1)),
+            (None, _) => Some(0),
+        };
+        self.select(index);
+    }
+
+    /// Moves down, stopping at the last of `len` rows.
+    pub fn down(&mut self, len: usize) {
+        let index = match (self.selected(), len) {
+            (_, 0) => None,
+            (Some(index), len) => Some((index + 1).min(len - 1)),
+            (None, _) => Some(0),
+        };
+        self.select(index);
+    }
+
+    /// Pulls the selection back inside `len` rows, e.g. after a delete.
+    pub fn clamp(&mut self, len: usize) {
+        let index = match (self.selected(), len) {
+            (_, 0) => None,
+            (Some(index), len) => Some(index.min(len - 1)),
+            (None, _) => None,
+        };
+        self.select(index);
+    }
+
+    /// The id of the row at `index` as of the last restore.
+    pub fn id_at(&self, index: usize) -> Option<usize> {
+        self.ids.get(index).copied()
+    }
+
+    /// Re-selects the previously selected row among freshly fetched `ids`,
+    /// following it to its new position. When it is gone, e.g. deleted, the
+    /// selection stays at the same position, clamped to the new rows.
+    pub fn restore(&mut self, ids: Vec<usize>) {
+        let previous = self.selected().and_then(|index| self.id_at(index));
+        match previous.and_then(|id| ids.iter().position(|&other| other == id)) {
+            Some(index) => self.select(Some(index)),
+            None => self.clamp(ids.len()),
+        }
+        self.ids = ids;
+    }
+
+    /// Selects the first of `len` rows, or nothing when there are none.
+    pub fn first(&mut self, len: usize) {
+        self.select((len > 0).then_some(0));
+    }
+
+    pub fn list_state(&mut self) -> &mut ListState {
+        &mut self.state
+    }
+
+    /// Like `list_state`, for a pane where item `index` is shown on row
+    /// `row(index)`, e.g. because of section headers.
+    pub fn rows_state(&mut self, row: impl Fn(usize) -> usize) -> &mut ListState {
+        self.rows.select(self.selected().map(row));
+        &mut self.rows
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::database::todo_dir;
+
+/// A long running `todo` command that can be installed as a user service.
+struct Service {
+    name: &'static str,
+    description: &'static str,
+    args: &'static [&'static str],
+}
+
+const SERVICES: &[Service] = &[
+    Service {
+        name: "listen",
+        description: "todo-tui quick-add socket",
+        args: &["listen"],
+    },
+    Service {
+        name: "jobs",
+        description: "todo-tui scheduled jobs",
+        args: &["daemon", "run"],
+    },
+];
+
+/// Writes a user level service definition for every daemon: systemd units on
+/// Linux and launchd agents on macOS.
+pub fn install() -> Result<(), Box<dyn Error>> {
+    let exe = env::current_exe()?;
+    let home: PathBuf = env::var_os("HOME").ok_or("Could not determine home directory")?.into();
+    let log_dir = todo_dir();
+
+    for service in SERVICES {
+        let path = if cfg!(target_os = "macos") {
+            let dir = home.join("Library/LaunchAgents");
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(format!("com.todo-tui.{}.plist", service.name));
+            fs::write(&path, launchd_plist(service, &exe, &log_dir))?;
+            path
+        } else {
+            let dir = home.join(".config/systemd/user");
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(format!("todo-{}.service", service.name));
+            fs::write(&path, systemd_unit(service, &exe))?;
+            path
+        };
+        println!("Wrote {}", path.display());
+    }
+
+    if cfg!(target_os = "macos") {
+        println!("Load with: launchctl load ~/Library/LaunchAgents/com.todo-tui.<name>.plist");
+    } else {
+        println!("Enable with: systemctl --user daemon-reload && systemctl --user enable --now todo-<name>.service");
+    }
+    Ok(())
+}
+
+fn systemd_unit(service: &Service, exe: &Path) -> String {
+    format!(
+        "[Unit]
+Description={description}
+
+[Service]
+ExecStart={command}
+Restart=on-failure
+
+[Install]
+WantedBy=default.target
+",
+        description = service.description,
+        command = std::iter::once(exe.display().to_string())
+            .chain(service.args.iter().map(|a| a.to_string()))
+            .map(|word| systemd_quote(&word))
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Quotes a word of an ExecStart line, so paths with spaces stay one word
+/// and `%` and `$` aren't expanded by systemd.
+fn systemd_quote(word: &str) -> String {
+    let escaped = word.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+fn launchd_plist(service: &Service, exe: &Path, log_dir: &Path) -> String {
+    let arguments: String = std::iter::once(exe.display().to_string())
+        .chain(service.args.iter().map(|a| a.to_string()))
+        .map(|a| format!("        <string>{}</string>\n", xml_escape(&a)))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>com.todo-tui.{name}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        name = service.name,
+        arguments = arguments,
+        log = xml_escape(&log_dir.join(format!("{}.log", service.name)).display().to_string()),
+    )
+}
@@ -1,12 +1,17 @@
-use std::{env, path::PathBuf, fs};
+use std::{env, fmt, io, path::{Path, PathBuf}, fs, sync::RwLock};
 
-use crate::model::{Todo, TodoList};
-use chrono::{Local, NaiveDate};
-use rusqlite::{params, Connection, Result};
+use crate::model::{self, Delta, Milestone, Priority, Relation, TimeSlot, Todo, TodoList};
+use crate::query::TodoQuery;
+use chrono::{Days, Local, NaiveDate, NaiveDateTime};
+use rusqlite::{functions::FunctionFlags, params, params_from_iter, Connection, Result, Row};
 
 #[derive(Debug)]
 pub enum DatabaseError {
     RusqliteError(rusqlite::Error),
+    IoError(io::Error),
+    /// The file opened but `PRAGMA quick_check` found damage, with what it
+    /// reported.
+    Corrupt(String),
 }
 
 impl From<rusqlite::Error> for DatabaseError {
@@ -15,9 +20,27 @@ impl From<rusqlite::Error> for DatabaseError {
     }
 }
 
+impl From<io::Error> for DatabaseError {
+    fn from(error: io::Error) -> Self {
+        DatabaseError::IoError(error)
+    }
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::RusqliteError(e) => write!(f, "{}", e),
+            DatabaseError::IoError(e) => write!(f, "{}", e),
+            DatabaseError::Corrupt(report) => write!(f, "the database is damaged: {}", report),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
 pub type SqlResult<T> = std::result::Result<T, DatabaseError>;
 
-fn get_path() -> PathBuf {
+pub fn todo_dir() -> PathBuf {
     let home_dir: PathBuf = match env::var_os("HOME") {
         Some(home) => home.into(),
         None => {
@@ -27,9 +50,41 @@ fn get_path() -> PathBuf {
     };
     let dir = home_dir.join(".todo/");
     if !dir.is_dir() {
+        fs::create_dir_all(&dir).ok();
+    }
+    dir
+}
+
+/// Overrides the default `~/.todo/todos.sqlite` for the rest of the process.
+static DATABASE_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub fn get_path() -> PathBuf {
+    match DATABASE_PATH.read().ok().and_then(|path| path.clone()) {
+        Some(path) => path,
+        None => todo_dir().join("todos.sqlite"),
+    }
+}
+
+/// Switches every following call to the database at `path`, or back to the
+/// default one.
+pub fn use_database(path: Option<PathBuf>) {
+    if let Some(dir) = path.as_ref().and_then(|path| path.parent()) {
         fs::create_dir_all(dir).ok();
     }
-    return home_dir.join(".todo/todos.sqlite");
+    if let Ok(mut current) = DATABASE_PATH.write() {
+        *current = path;
+    }
+}
+
+/// Switches every following call to a throwaway in-memory database. The
+/// database only lives as long as a connection to it is open, so the
+/// returned connection must be kept around while it is in use.
+pub fn use_in_memory_database(name: &str) -> SqlResult<Connection> {
+    let uri = format!("file:{}?mode=memory&cache=shared", name);
+    if let Ok(mut path) = DATABASE_PATH.write() {
+        *path = Some(PathBuf::from(uri));
+    }
+    open_db()
 }
 
 pub fn open_db() -> SqlResult<Connection> {
@@ -38,6 +93,153 @@ pub fn open_db() -> SqlResult<Connection> {
     Ok(conn)
 }
 
+/// Opens the database and checks it for damage, which `open_db` alone only
+/// notices when the first page is affected.
+pub fn check_db() -> SqlResult<()> {
+    let conn = open_db()?;
+    let report: String = conn.query_row("PRAGMA quick_check", params![], |row| row.get(0))?;
+    match report.as_str() {
+        "ok" => Ok(()),
+        _ => Err(DatabaseError::Corrupt(report)),
+    }
+}
+
+/// Where copies of the database in use are kept, named
+/// `<database>-<timestamp>.sqlite`. Every database has its own directory,
+/// keyed on its full path, so workspaces whose files share a name never
+/// see each other's backups.
+pub fn backup_dir() -> PathBuf {
+    let path = get_path();
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    todo_dir().join("backups").join(format!("{}-{:016x}", stem, path_hash(&path)))
+}
+
+/// FNV-1a of the path, which unlike `DefaultHasher` stays the same across
+/// Rust releases, so the backups are found again after an upgrade.
+fn path_hash(path: &Path) -> u64 {
+    path.to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Writes a consistent copy of the database in use to `backup_dir`, then
+/// removes all but the newest `keep` backups of it. Returns the new backup.
+pub fn backup(keep: usize) -> SqlResult<PathBuf> {
+    let stem = get_path().file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let dir = backup_dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}-{}.sqlite", stem, Local::now().format("%Y%m%d-%H%M%S")));
+    let conn = open_db()?;
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|other| is_backup_of(other, &stem))
+        .collect();
+    backups.sort();
+    let stale = backups.len().saturating_sub(keep.max(1));
+    for old in &backups[..stale] {
+        fs::remove_file(old)?;
+    }
+    Ok(path)
+}
+
+/// Whether `path` is named exactly `<stem>-YYYYMMDD-HHMMSS.sqlite`.
+fn is_backup_of(path: &Path, stem: &str) -> bool {
+    let timestamp = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(stem))
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|rest| rest.strip_suffix(".sqlite"));
+    // The length rules out unpadded numbers, which the parser accepts.
+    timestamp.is_some_and(|t| t.len() == 15 && NaiveDateTime::parse_from_str(t, "%Y%m%d-%H%M%S").is_ok())
+}
+
+/// The newest backup of the database in use.
+pub fn latest_backup() -> Option<PathBuf> {
+    let stem = get_path().file_stem()?.to_string_lossy().to_string();
+    fs::read_dir(backup_dir())
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_backup_of(path, &stem))
+        // The timestamp sorts the names by age.
+        .max()
+}
+
+/// Moves a damaged database out of the way, to `<database>.broken`, and
+/// returns where it went.
+fn set_aside(path: &Path) -> SqlResult<PathBuf> {
+    let broken = path.with_extension("broken");
+    if path.exists() {
+        fs::rename(path, &broken)?;
+    }
+    Ok(broken)
+}
+
+/// Replaces the database in use with its latest backup and returns the
+/// backup's path. The replaced file is kept, see `set_aside`.
+pub fn restore_latest_backup() -> SqlResult<PathBuf> {
+    let Some(backup) = latest_backup() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no backups in {}", backup_dir().display())).into());
+    };
+    let path = get_path();
+    set_aside(&path)?;
+    fs::copy(&backup, &path)?;
+    Ok(backup)
+}
+
+/// Tables copied by `repair`. The changes log is rebuilt by its triggers.
+const TABLES: [&str; 7] = ["lists", "todos", "relations", "attachments", "fields", "milestones", "plugin_data"];
+
+/// Copies every row that can still be read into a fresh database, which
+/// then replaces the one in use. Reading a table stops at its first damaged
+/// row. Returns how many rows were kept; the damaged file is kept too, see
+/// `set_aside`.
+pub fn repair() -> SqlResult<usize> {
+    let path = get_path();
+    let repaired = path.with_extension("repaired");
+    if repaired.exists() {
+        fs::remove_file(&repaired)?;
+    }
+    let kept = {
+        let conn = Connection::open(&repaired)?;
+        init_db(&conn)?;
+        conn.execute("ATTACH DATABASE ?1 AS damaged", params![path.to_string_lossy()])?;
+        TABLES.iter().map(|table| salvage_table(&conn, table).unwrap_or(0)).sum()
+    };
+    set_aside(&path)?;
+    fs::rename(&repaired, &path)?;
+    Ok(kept)
+}
+
+fn salvage_table(conn: &Connection, table: &str) -> SqlResult<usize> {
+    // The damaged copy may predate columns added since.
+    let columns = |schema: &str| -> SqlResult<Vec<String>> {
+        let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?;
+        let names = stmt.query_map(params![], |row| row.get(1))?.collect::<Result<Vec<String>>>()?;
+        Ok(names)
+    };
+    let current = columns("main")?;
+    let shared: Vec<String> = columns("damaged")?.into_iter().filter(|c| current.contains(c)).collect();
+    if shared.is_empty() {
+        return Ok(0);
+    }
+    let list = shared.join(", ");
+    let placeholders = vec!["?"; shared.len()].join(", ");
+    let mut select = conn.prepare(&format!("SELECT {} FROM damaged.{}", list, table))?;
+    let mut insert = conn.prepare(&format!("INSERT OR IGNORE INTO main.{} ({}) VALUES ({})", table, list, placeholders))?;
+    let mut rows = select.query(params![])?;
+    let mut kept = 0;
+    while let Ok(Some(row)) = rows.next() {
+        let values = (0..shared.len())
+            .map(|i| row.get::<_, rusqlite::types::Value>(i))
+            .collect::<Result<Vec<_>>>()?;
+        kept += insert.execute(params_from_iter(values.iter()))?;
+    }
+    Ok(kept)
+}
+
 fn init_db(conn: &Connection) -> SqlResult<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS todos (
@@ -60,27 +262,201 @@ fn init_db(conn: &Connection) -> SqlResult<()> {
         params![],
     )?;
 
-    Ok(())
-}
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS relations (
+            todo_id INTEGER NOT NULL,
+            related_id INTEGER NOT NULL,
+            relation TEXT NOT NULL,
+            PRIMARY KEY (todo_id, related_id, relation)
+        )",
+        params![],
+    )?;
 
-pub fn add_todo(todo: &Todo) -> SqlResult<()> {
-    let conn = open_db()?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS attachments (
+            todo_id INTEGER NOT NULL,
+            url TEXT NOT NULL
+        )",
+        params![],
+    )?;
 
     conn.execute(
-        "INSERT INTO todos (list_id, title, description, due_date, completed, completed_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![
-            todo.list_id,
-            todo.title,
-            todo.description,
-            todo.due_date.map(|d| d.to_string()),
-            todo.completed,
-            todo.completed_date.map(|d| d.to_string())
-        ],
+        "CREATE TABLE IF NOT EXISTS fields (
+            todo_id INTEGER NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (todo_id, key)
+        )",
+        params![],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS milestones (
+            id INTEGER PRIMARY KEY,
+            list_id INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            collapsed BOOLEAN NOT NULL DEFAULT false
+        )",
+        params![],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS plugin_data (
+            plugin TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (plugin, key)
+        )",
+        params![],
+    )?;
+
+    // The last change to every todo, numbered in order, so changes can be
+    // synced from a cursor on. Deleted todos leave a tombstone.
+    let logged = table_exists(conn, "changes")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS changes (
+            todo_id INTEGER PRIMARY KEY,
+            seq INTEGER NOT NULL,
+            deleted BOOLEAN NOT NULL
+        )",
+        params![],
     )?;
+    for (event, row, deleted) in [("INSERT", "NEW", false), ("UPDATE", "NEW", false), ("DELETE", "OLD", true)] {
+        conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS todos_{event}_logged AFTER {event} ON todos BEGIN
+                    INSERT OR REPLACE INTO changes (todo_id, seq, deleted)
+                    VALUES ({row}.id, (SELECT COALESCE(MAX(seq), 0) + 1 FROM changes), {deleted});
+                END",
+                event = event.to_lowercase(),
+                row = row,
+                deleted = deleted,
+            ),
+            params![],
+        )?;
+    }
+    if !logged {
+        conn.execute("INSERT INTO changes (todo_id, seq, deleted) SELECT id, 1, false FROM todos", params![])?;
+    }
+
+    add_column_if_missing(conn, "todos", "created_date", "TEXT")?;
+    add_column_if_missing(conn, "todos", "slot", "TEXT")?;
+    // completed_date held either a bare date or a full timestamp with
+    // fractions of a second, which readers cut down to the date.
+    // completed_at replaces it and always holds a timestamp.
+    let migrate_completed = add_column_if_missing(conn, "todos", "completed_at", "TEXT")?;
+    add_column_if_missing(conn, "todos", "priority", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "lists", "due_date", "TEXT")?;
+    add_column_if_missing(conn, "todos", "milestone_id", "INTEGER")?;
+    if migrate_completed {
+        conn.execute(
+            "UPDATE todos SET completed_at = CASE
+                WHEN length(completed_date) = 10 THEN completed_date || ' 00:00:00'
+                ELSE substr(completed_date, 1, 19)
+            END
+            WHERE completed_date IS NOT NULL",
+            params![],
+        )?;
+    }
 
     Ok(())
 }
 
+fn table_exists(conn: &Connection, table: &str) -> SqlResult<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+        params![table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Returns whether the column was added.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> SqlResult<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map(params![], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), params![])?;
+    }
+    Ok(!exists)
+}
+
+/// Dates are stored as text, sometimes with a time part appended, so only the
+/// leading `YYYY-MM-DD` is considered.
+fn parse_date(value: Option<String>) -> Option<NaiveDate> {
+    value.and_then(|s| NaiveDate::parse_from_str(s.get(..10)?, "%Y-%m-%d").ok())
+}
+
+/// How completed_at is stored.
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn parse_timestamp(value: Option<String>) -> Option<NaiveDateTime> {
+    value.and_then(|s| NaiveDateTime::parse_from_str(&s, TIMESTAMP_FORMAT).ok())
+}
+
+/// Reads a row of `SELECT * FROM todos` by column name, migrations add
+/// columns at the end so their position depends on the database's history.
+fn todo_from_row(row: &Row) -> Result<Todo> {
+    Ok(Todo {
+        id: row.get("id")?,
+        list_id: row.get("list_id")?,
+        title: row.get("title")?,
+        description: row.get("description")?,
+        due_date: parse_date(row.get("due_date")?),
+        completed: row.get("completed")?,
+        created_date: parse_date(row.get("created_date")?),
+        slot: row.get::<_, Option<String>>("slot")?.and_then(|s| TimeSlot::parse(&s)),
+        completed_at: parse_timestamp(row.get("completed_at")?),
+        priority: Priority::from_level(row.get("priority")?),
+        milestone_id: row.get("milestone_id")?,
+        dependencies: vec![], // Fetch dependencies if needed.
+    })
+}
+
+/// Inserts the todo and returns its new id.
+pub fn add_todo(todo: &Todo) -> SqlResult<usize> {
+    let ids = add_todos(std::slice::from_ref(todo))?;
+    Ok(ids[0])
+}
+
+/// Inserts all todos in one transaction and returns their new ids, in order.
+pub fn add_todos(todos: &[Todo]) -> SqlResult<Vec<usize>> {
+    let mut conn = open_db()?;
+    let tx = conn.transaction()?;
+    let ids = insert_todos(&tx, todos)?;
+    tx.commit()?;
+    Ok(ids)
+}
+
+fn insert_todos(conn: &Connection, todos: &[Todo]) -> SqlResult<Vec<usize>> {
+    let mut ids = Vec::with_capacity(todos.len());
+    {
+        let mut stmt = conn.prepare(
+            "INSERT INTO todos (list_id, title, description, due_date, completed, completed_at, created_date, slot, priority, milestone_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for todo in todos {
+            stmt.execute(params![
+                todo.list_id,
+                todo.title,
+                todo.description,
+                todo.due_date.map(|d| d.to_string()),
+                todo.completed,
+                todo.completed_at.map(|d| d.format(TIMESTAMP_FORMAT).to_string()),
+                todo.created_date.map(|d| d.to_string()),
+                todo.slot.map(|s| s.to_string()),
+                todo.priority.level(),
+                todo.milestone_id
+            ])?;
+            ids.push(conn.last_insert_rowid() as usize);
+        }
+    }
+    Ok(ids)
+}
+
 pub fn update_todo(todo: &Todo) -> SqlResult<()> {
     let conn = open_db()?;
 
@@ -91,7 +467,10 @@ pub fn update_todo(todo: &Todo) -> SqlResult<()> {
         description = ?4,
         due_date = ?5,
         completed = ?6,
-        completed_date = ?7
+        completed_at = ?7,
+        slot = ?8,
+        priority = ?9,
+        milestone_id = ?10
         WHERE id = ?1
         ",
         params![
@@ -101,17 +480,21 @@ pub fn update_todo(todo: &Todo) -> SqlResult<()> {
             todo.description,
             todo.due_date.map(|d| d.to_string()),
             todo.completed,
-            todo.completed_date.map(|d| d.to_string())
+            todo.completed_at.map(|d| d.format(TIMESTAMP_FORMAT).to_string()),
+            todo.slot.map(|s| s.to_string()),
+            todo.priority.level(),
+            todo.milestone_id
         ],
     )?;
+    prune_milestones(&conn)?;
 
     Ok(())
 }
 
 pub fn toggle_todo_completion(todo_id: usize, completed: bool) -> SqlResult<()> {
     let conn = open_db()?;
-    let completed_date = if completed {
-        Some(Local::now().naive_local().to_string())
+    let completed_at = if completed {
+        Some(Local::now().naive_local().format(TIMESTAMP_FORMAT).to_string())
     } else {
         None
     };
@@ -119,97 +502,518 @@ pub fn toggle_todo_completion(todo_id: usize, completed: bool) -> SqlResult<()>
     conn.execute(
         "UPDATE todos SET 
             completed = ?2, 
-            completed_date = ?3
+            completed_at = ?3
         WHERE id = ?1",
-        params![todo_id, completed, completed_date],
+        params![todo_id, completed, completed_at],
     )?;
 
     Ok(())
 }
 
-pub fn delete_todo(todo_id: usize) -> SqlResult<()> {
+/// Raises incomplete todos overdue by more than `days` to high priority, and
+/// those overdue by more than twice as long to urgent. Never lowers a
+/// priority. Returns how many todos were raised.
+pub fn escalate_overdue(days: u64, today: NaiveDate) -> SqlResult<usize> {
+    let Some(high_before) = today.checked_sub_days(Days::new(days)) else {
+        return Ok(0);
+    };
+    let urgent_before = today
+        .checked_sub_days(Days::new(days.saturating_mul(2)))
+        .unwrap_or(NaiveDate::MIN);
     let conn = open_db()?;
-    conn.execute("DELETE FROM todos WHERE id = ?", params![todo_id])?;
-    Ok(())
+    let raised = conn.execute(
+        "UPDATE todos SET priority = CASE WHEN substr(due_date, 1, 10) < ?1 THEN ?3 ELSE ?4 END
+        WHERE completed = false AND substr(due_date, 1, 10) < ?2
+        AND priority < CASE WHEN substr(due_date, 1, 10) < ?1 THEN ?3 ELSE ?4 END",
+        params![
+            urgent_before.to_string(),
+            high_before.to_string(),
+            Priority::Urgent.level(),
+            Priority::High.level()
+        ],
+    )?;
+    Ok(raised)
 }
 
-pub fn fetch_incomplete_todos(date: NaiveDate) -> SqlResult<Vec<Todo>> {
+/// The number of todos, and the number of incomplete todos per list title.
+pub fn count_todos() -> SqlResult<(usize, Vec<(String, usize)>)> {
     let conn = open_db()?;
+    let total: usize = conn.query_row("SELECT COUNT(*) FROM todos", params![], |row| row.get(0))?;
+    let mut stmt = conn.prepare(
+        "SELECT lists.title, COUNT(todos.id) FROM lists
+         JOIN todos ON todos.list_id = lists.id AND NOT todos.completed
+         GROUP BY lists.id",
+    )?;
+    let open = stmt
+        .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok((total, open))
+}
+
+/// Every tag with the number of todos using it, by name.
+pub fn fetch_tags() -> SqlResult<Vec<(String, usize)>> {
+    let mut counts: Vec<(String, usize)> = vec![];
+    for todo in fetch_todos(&TodoQuery::new())? {
+        let mut tags = todo.tags();
+        tags.sort();
+        tags.dedup();
+        for tag in tags {
+            match counts.iter_mut().find(|(name, _)| *name == tag) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((tag, 1)),
+            }
+        }
+    }
+    counts.sort_by_key(|(name, _)| name.to_lowercase());
+    Ok(counts)
+}
+
+/// Renames the tag `from` to `to` in the title and description of every
+/// todo, or removes it when `to` is `None`. Todos that already have `to`
+/// just lose `from`, which merges the two. Returns how many todos changed.
+pub fn retag(from: &str, to: Option<&str>) -> SqlResult<usize> {
+    let todos = fetch_todos(&TodoQuery::new())?;
+    let mut conn = open_db()?;
+    let tx = conn.transaction()?;
+    let mut changed = 0;
+    {
+        let mut update = tx.prepare("UPDATE todos SET title = ?2, description = ?3 WHERE id = ?1")?;
+        for todo in todos {
+            let tags = todo.tags();
+            if !tags.iter().any(|tag| tag == from) {
+                continue;
+            }
+            let to = to.filter(|to| !tags.iter().any(|tag| tag == to));
+            let title = model::retag(&todo.title, from, to);
+            let description = todo.description.as_deref().map(|d| model::retag(d, from, to));
+            update.execute(params![todo.id, title, description])?;
+            changed += 1;
+        }
+    }
+    tx.commit()?;
+    Ok(changed)
+}
+
+/// Deletes the todos completed before `date`, returning how many.
+pub fn delete_completed_before(date: NaiveDate) -> SqlResult<usize> {
+    let todos = fetch_todos(&TodoQuery::new().completed(true))?;
+    let ids: Vec<usize> = todos
+        .iter()
+        .filter(|t| t.completed_at.is_some_and(|at| at.date() < date))
+        .filter_map(|t| t.id)
+        .collect();
+    delete_todos(&ids)?;
+    Ok(ids.len())
+}
+
+pub fn delete_todo(todo_id: usize) -> SqlResult<()> {
+    delete_todos(&[todo_id])
+}
 
-    // println!("{}", date.format( "%Y-%m-%d").to_string());
-    let mut stmt = conn.prepare("SELECT * FROM todos WHERE completed = false and due_date <= ?")?;
-    let rows = stmt.query_map(params![date.format( "%Y-%m-%d").to_string()], |row| {
-        Ok(Todo {
+/// Deletes the todos together with their relations, attachments and fields,
+/// in one transaction.
+pub fn delete_todos(todo_ids: &[usize]) -> SqlResult<()> {
+    let mut conn = open_db()?;
+    let tx = conn.transaction()?;
+    {
+        let mut todos = tx.prepare("DELETE FROM todos WHERE id = ?")?;
+        let mut relations = tx.prepare("DELETE FROM relations WHERE todo_id = ?1 OR related_id = ?1")?;
+        let mut attachments = tx.prepare("DELETE FROM attachments WHERE todo_id = ?")?;
+        let mut fields = tx.prepare("DELETE FROM fields WHERE todo_id = ?")?;
+        for todo_id in todo_ids {
+            todos.execute(params![todo_id])?;
+            relations.execute(params![todo_id])?;
+            attachments.execute(params![todo_id])?;
+            fields.execute(params![todo_id])?;
+        }
+    }
+    prune_milestones(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Milestones only exist through their todos, the last one leaving removes it.
+fn prune_milestones(conn: &Connection) -> SqlResult<()> {
+    conn.execute(
+        "DELETE FROM milestones WHERE id NOT IN (SELECT milestone_id FROM todos WHERE milestone_id IS NOT NULL)",
+        params![],
+    )?;
+    Ok(())
+}
+
+/// The milestones of a list in the order they are shown.
+pub fn fetch_milestones(list_id: usize) -> SqlResult<Vec<Milestone>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, title, position, collapsed FROM milestones WHERE list_id = ? ORDER BY position, id",
+    )?;
+    let rows = stmt.query_map(params![list_id], |row| {
+        Ok(Milestone {
             id: row.get(0)?,
-            list_id: row.get(1)?,
-            title: row.get(2)?,
-            description: row.get(3)?,
-            due_date: row
-                .get::<_, Option<String>>(4)?
-                .and_then(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok()),
-            completed: row.get(5)?,
-            completed_date: row
-                .get::<_, Option<String>>(6)?
-                .and_then(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok()),
-            dependencies: vec![], // Fetch dependencies if needed.
+            title: row.get(1)?,
+            position: row.get(2)?,
+            collapsed: row.get(3)?,
         })
     })?;
+    Ok(rows.filter_map(Result::ok).collect())
+}
 
-    let todos: Vec<Todo> = rows.filter_map(Result::ok).collect();
+/// Adds a milestone after the existing ones of its list and returns its id.
+pub fn add_milestone(list_id: usize, title: &str) -> SqlResult<usize> {
+    insert_milestone(&open_db()?, list_id, title)
+}
 
-    Ok(todos)
+fn insert_milestone(conn: &Connection, list_id: usize, title: &str) -> SqlResult<usize> {
+    conn.execute(
+        "INSERT INTO milestones (list_id, title, position)
+        VALUES (?1, ?2, (SELECT coalesce(max(position) + 1, 0) FROM milestones WHERE list_id = ?1))",
+        params![list_id, title],
+    )?;
+    Ok(conn.last_insert_rowid() as usize)
 }
 
-pub fn fetch_todos(list_id: usize) -> SqlResult<Vec<Todo>> {
+pub fn set_milestone_collapsed(milestone_id: usize, collapsed: bool) -> SqlResult<()> {
     let conn = open_db()?;
+    conn.execute(
+        "UPDATE milestones SET collapsed = ?2 WHERE id = ?1",
+        params![milestone_id, collapsed],
+    )?;
+    Ok(())
+}
 
-    // Replace "WHERE 1" with your desired filter condition.
-    let mut stmt = conn.prepare("SELECT * FROM todos WHERE list_id = ?")?;
-    let rows = stmt.query_map(params![list_id], |row| {
-        Ok(Todo {
-            id: row.get(0)?,
-            list_id: row.get(1)?,
-            title: row.get(2)?,
-            description: row.get(3)?,
-            due_date: row
-                .get::<_, Option<String>>(4)?
-                .and_then(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok()),
-            completed: row.get(5)?,
-            completed_date: row
-                .get::<_, Option<String>>(6)?
-                .and_then(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok()),
-            dependencies: vec![], // Fetch dependencies if needed.
+pub fn expand_milestones(list_id: usize) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute("UPDATE milestones SET collapsed = false WHERE list_id = ?", params![list_id])?;
+    Ok(())
+}
+
+/// Swaps the positions of two milestones, used to move one past the other.
+pub fn swap_milestones(a: &Milestone, b: &Milestone) -> SqlResult<()> {
+    let mut conn = open_db()?;
+    let tx = conn.transaction()?;
+    tx.execute("UPDATE milestones SET position = ?2 WHERE id = ?1", params![a.id, b.position])?;
+    tx.execute("UPDATE milestones SET position = ?2 WHERE id = ?1", params![b.id, a.position])?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(feature = "url-titles")]
+pub fn set_todo_title(todo_id: usize, title: &str) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute("UPDATE todos SET title = ?2 WHERE id = ?1", params![todo_id, title])?;
+    Ok(())
+}
+
+pub fn add_attachment(todo_id: usize, url: &str) -> SqlResult<()> {
+    add_attachments(&[(todo_id, url.to_string())])
+}
+
+/// Inserts `(todo_id, url)` pairs in one transaction.
+pub fn add_attachments(attachments: &[(usize, String)]) -> SqlResult<()> {
+    let mut conn = open_db()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare("INSERT INTO attachments (todo_id, url) VALUES (?1, ?2)")?;
+        for (todo_id, url) in attachments {
+            stmt.execute(params![todo_id, url])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn fetch_attachments(todo_id: usize) -> SqlResult<Vec<String>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare("SELECT url FROM attachments WHERE todo_id = ?")?;
+    let rows = stmt.query_map(params![todo_id], |row| row.get(0))?;
+    let urls: Vec<String> = rows.filter_map(Result::ok).collect();
+    Ok(urls)
+}
+
+pub fn fetch_attachment_urls() -> SqlResult<Vec<String>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare("SELECT url FROM attachments")?;
+    let rows = stmt.query_map(params![], |row| row.get(0))?;
+    let urls: Vec<String> = rows.filter_map(Result::ok).collect();
+    Ok(urls)
+}
+
+/// Sets a custom field on the todo, replacing an earlier value of `key`.
+pub fn set_field(todo_id: usize, key: &str, value: &str) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO fields (todo_id, key, value) VALUES (?1, ?2, ?3)",
+        params![todo_id, key, value],
+    )?;
+    Ok(())
+}
+
+pub fn remove_field(todo_id: usize, key: &str) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute("DELETE FROM fields WHERE todo_id = ?1 AND key = ?2", params![todo_id, key])?;
+    Ok(())
+}
+
+/// The custom fields of the todo as `(key, value)`, sorted by key.
+pub fn fetch_fields(todo_id: usize) -> SqlResult<Vec<(String, String)>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare("SELECT key, value FROM fields WHERE todo_id = ? ORDER BY key")?;
+    let rows = stmt.query_map(params![todo_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let fields: Vec<(String, String)> = rows.filter_map(Result::ok).collect();
+    Ok(fields)
+}
+
+/// Every todo of the list with the custom field `key`, as `(todo id, value)`.
+pub fn fetch_field_values(list_id: usize, key: &str) -> SqlResult<Vec<(usize, String)>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare(
+        "SELECT todo_id, value FROM fields JOIN todos ON todos.id = fields.todo_id
+         WHERE todos.list_id = ?1 AND key = ?2",
+    )?;
+    let rows = stmt.query_map(params![list_id, key], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let values: Vec<(usize, String)> = rows.filter_map(Result::ok).collect();
+    Ok(values)
+}
+
+/// A value the plugin stored under `key`. Plugins only reach their own keys,
+/// see `plugin::Plugin`.
+pub fn fetch_plugin_value(plugin: &str, key: &str) -> SqlResult<Option<String>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare("SELECT value FROM plugin_data WHERE plugin = ?1 AND key = ?2")?;
+    let mut rows = stmt.query_map(params![plugin, key], |row| row.get(0))?;
+    Ok(rows.next().transpose()?)
+}
+
+pub fn set_plugin_value(plugin: &str, key: &str, value: &str) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO plugin_data (plugin, key, value) VALUES (?1, ?2, ?3)",
+        params![plugin, key, value],
+    )?;
+    Ok(())
+}
+
+pub fn remove_plugin_value(plugin: &str, key: &str) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute("DELETE FROM plugin_data WHERE plugin = ?1 AND key = ?2", params![plugin, key])?;
+    Ok(())
+}
+
+/// The keys the plugin stored values under, sorted.
+pub fn fetch_plugin_keys(plugin: &str) -> SqlResult<Vec<String>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare("SELECT key FROM plugin_data WHERE plugin = ? ORDER BY key")?;
+    let rows = stmt.query_map(params![plugin], |row| row.get(0))?;
+    let keys: Vec<String> = rows.filter_map(Result::ok).collect();
+    Ok(keys)
+}
+
+pub fn fetch_todo(todo_id: usize) -> SqlResult<Option<Todo>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare("SELECT * FROM todos WHERE id = ?")?;
+    let mut rows = stmt.query_map(params![todo_id], todo_from_row)?;
+    Ok(rows.next().transpose()?)
+}
+
+pub fn add_relation(relation: &Relation) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO relations (todo_id, related_id, relation) VALUES (?1, ?2, ?3)",
+        params![relation.todo_id, relation.related_id, relation.kind],
+    )?;
+    Ok(())
+}
+
+/// Relations in both directions, always returned with `todo_id` set to the
+/// given todo.
+pub fn fetch_relations(todo_id: usize) -> SqlResult<Vec<Relation>> {
+    let conn = open_db()?;
+    let mut stmt = conn.prepare(
+        "SELECT todo_id, related_id, relation FROM relations WHERE todo_id = ?1
+         UNION SELECT related_id, todo_id, relation FROM relations WHERE related_id = ?1",
+    )?;
+    let rows = stmt.query_map(params![todo_id], |row| {
+        Ok(Relation {
+            todo_id: row.get(0)?,
+            related_id: row.get(1)?,
+            kind: row.get(2)?,
         })
     })?;
 
-    let todos: Vec<Todo> = rows.filter_map(Result::ok).collect();
+    let relations: Vec<Relation> = rows.filter_map(Result::ok).collect();
+    Ok(relations)
+}
 
+/// Fetches the todos matching `query` with a single parameterized
+/// statement.
+pub fn fetch_todos(query: &TodoQuery) -> SqlResult<Vec<Todo>> {
+    let (sql, values) = query.to_sql();
+    let conn = open_db()?;
+    // SQLite's own case folding is ASCII only, tags are compared in Rust.
+    conn.create_scalar_function("has_tag", 3, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, |ctx| {
+        let title: String = ctx.get(0)?;
+        let description: Option<String> = ctx.get(1)?;
+        let tag: String = ctx.get(2)?;
+        Ok(model::has_tag(&title, &tag) || description.is_some_and(|d| model::has_tag(&d, &tag)))
+    })?;
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values.iter()), todo_from_row)?;
+    let todos: Vec<Todo> = rows.filter_map(Result::ok).collect();
     Ok(todos)
 }
 
-pub fn add_list(list: &TodoList) -> SqlResult<()> {
+/// Todos added, changed or deleted after `cursor`, 0 for all of them. Only
+/// the todos themselves are tracked, not their fields, links or attachments.
+pub fn fetch_changes(cursor: i64) -> SqlResult<Delta> {
     let conn = open_db()?;
-    conn.execute("INSERT INTO lists (title) VALUES (?)", params![list.title])?;
+    let latest: i64 = conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM changes", params![], |row| row.get(0))?;
+    let mut stmt = conn.prepare(
+        "SELECT todos.* FROM todos JOIN changes ON changes.todo_id = todos.id
+         WHERE changes.seq > ? ORDER BY changes.seq",
+    )?;
+    let todos = stmt.query_map(params![cursor], todo_from_row)?.collect::<Result<Vec<_>>>()?;
+    let mut stmt = conn.prepare("SELECT todo_id FROM changes WHERE deleted AND seq > ? ORDER BY seq")?;
+    let deleted = stmt.query_map(params![cursor], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
+    Ok(Delta {
+        cursor: latest.max(cursor),
+        todos,
+        deleted,
+    })
+}
+
+/// Inserts the list and returns its new id.
+pub fn add_list(list: &TodoList) -> SqlResult<usize> {
+    let conn = open_db()?;
+    conn.execute(
+        "INSERT INTO lists (title, due_date) VALUES (?1, ?2)",
+        params![list.title, list.due_date.map(|d| d.to_string())],
+    )?;
+    Ok(conn.last_insert_rowid() as usize)
+}
+
+/// Inserts the list with its todos in one transaction, so a failure leaves
+/// nothing behind. Every todo goes into the new list, under the milestone
+/// named next to it, which is created on first use. Returns the list's id.
+pub fn add_list_with_todos(list: &TodoList, todos: &[(Todo, Option<String>)]) -> SqlResult<usize> {
+    let mut conn = open_db()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO lists (title, due_date) VALUES (?1, ?2)",
+        params![list.title, list.due_date.map(|d| d.to_string())],
+    )?;
+    let list_id = tx.last_insert_rowid() as usize;
+    let mut milestones: Vec<(&str, usize)> = vec![];
+    let mut placed = Vec::with_capacity(todos.len());
+    for (todo, milestone) in todos {
+        let milestone_id = match milestone.as_deref() {
+            Some(name) => match milestones.iter().find(|(title, _)| *title == name) {
+                Some((_, id)) => Some(*id),
+                None => {
+                    let id = insert_milestone(&tx, list_id, name)?;
+                    milestones.push((name, id));
+                    Some(id)
+                }
+            },
+            None => None,
+        };
+        placed.push(Todo {
+            list_id,
+            milestone_id,
+            ..todo.clone()
+        });
+    }
+    insert_todos(&tx, &placed)?;
+    tx.commit()?;
+    Ok(list_id)
+}
+
+pub fn update_list(list: &TodoList) -> SqlResult<()> {
+    let conn = open_db()?;
+    conn.execute(
+        "UPDATE lists SET title = ?2, due_date = ?3 WHERE id = ?1",
+        params![list.id, list.title, list.due_date.map(|d| d.to_string())],
+    )?;
     Ok(())
 }
 
 pub fn delete_list(list_id: usize) -> SqlResult<()> {
     let conn = open_db()?;
     conn.execute("DELETE FROM lists WHERE id = ?", params![list_id])?;
+    conn.execute("DELETE FROM milestones WHERE list_id = ?", params![list_id])?;
+    conn.execute(
+        "DELETE FROM relations WHERE todo_id IN (SELECT id FROM todos WHERE list_id = ?1)
+         OR related_id IN (SELECT id FROM todos WHERE list_id = ?1)",
+        params![list_id],
+    )?;
+    conn.execute(
+        "DELETE FROM attachments WHERE todo_id IN (SELECT id FROM todos WHERE list_id = ?)",
+        params![list_id],
+    )?;
+    conn.execute(
+        "DELETE FROM fields WHERE todo_id IN (SELECT id FROM todos WHERE list_id = ?)",
+        params![list_id],
+    )?;
     conn.execute("DELETE FROM todos WHERE list_id = ?", params![list_id])?;
     Ok(())
 }
 
+fn query_all_todos(conn: &Connection) -> SqlResult<Vec<Todo>> {
+    let mut stmt = conn.prepare("SELECT * FROM todos")?;
+    let rows = stmt.query_map(params![], todo_from_row)?;
+    let todos: Vec<Todo> = rows.filter_map(Result::ok).collect();
+    Ok(todos)
+}
+
+/// Reads lists and todos from another database file, e.g. an old copy of
+/// `todos.sqlite`. The file is copied first so that bringing its schema up
+/// to date never modifies the original.
+pub fn fetch_snapshot(path: &Path) -> SqlResult<(Vec<TodoList>, Vec<Todo>)> {
+    let copy = env::temp_dir().join(format!("todo-snapshot-{}.sqlite", std::process::id()));
+    fs::copy(path, &copy)?;
+    let result = (|| {
+        let conn = Connection::open(&copy)?;
+        init_db(&conn)?;
+        Ok((query_lists(&conn)?, query_all_todos(&conn)?))
+    })();
+    fs::remove_file(&copy).ok();
+    result
+}
+
 pub fn fetch_lists() -> SqlResult<Vec<TodoList>> {
     let conn = open_db()?;
+    query_lists(&conn)
+}
+
+fn query_lists(conn: &Connection) -> SqlResult<Vec<TodoList>> {
     let mut stmt = conn.prepare("SELECT * FROM lists")?;
     let rows = stmt.query_map(params![], |row| {
         Ok(TodoList {
             id: row.get(0)?,
             title: row.get(1)?,
+            due_date: parse_date(row.get(2)?),
         })
     })?;
 
     let lists: Vec<TodoList> = rows.filter_map(Result::ok).collect();
     Ok(lists)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_only_exact_backup_names() {
+        assert!(is_backup_of(Path::new("todos-20240301-093000.sqlite"), "todos"));
+        assert!(!is_backup_of(Path::new("todos-work-20240301-093000.sqlite"), "todos"));
+        assert!(!is_backup_of(Path::new("todos-20240301-093000.sqlite-journal"), "todos"));
+        assert!(!is_backup_of(Path::new("todos-notes.sqlite"), "todos"));
+    }
+
+    #[test]
+    fn keeps_backups_of_same_named_databases_apart() {
+        let home = path_hash(Path::new("/home/me/.todo/todos.sqlite"));
+        assert_eq!(home, path_hash(Path::new("/home/me/.todo/todos.sqlite")));
+        assert_ne!(home, path_hash(Path::new("/home/me/work/todos.sqlite")));
+    }
+}
@@ -0,0 +1,85 @@
+use std::{error::Error, path::Path};
+
+use chrono::NaiveDate;
+
+use crate::{
+    database::{fetch_lists, fetch_snapshot, fetch_todos},
+    model::{Todo, TodoList},
+    query::TodoQuery,
+};
+
+#[derive(Default)]
+struct Changes<'a> {
+    added: Vec<&'a Todo>,
+    completed: Vec<&'a Todo>,
+    deleted: Vec<&'a Todo>,
+}
+
+/// Compares two database files, e.g. copies of `~/.todo/todos.sqlite` taken
+/// at different times.
+pub fn diff_files(old: &Path, new: &Path) -> Result<(), Box<dyn Error>> {
+    let (old_lists, old_todos) = fetch_snapshot(old)?;
+    let (new_lists, new_todos) = fetch_snapshot(new)?;
+
+    let mut changes = Changes::default();
+    for todo in &new_todos {
+        match old_todos.iter().find(|t| t.id == todo.id) {
+            None => changes.added.push(todo),
+            Some(old) if todo.completed && !old.completed => changes.completed.push(todo),
+            Some(_) => {}
+        }
+    }
+    // Todos that were both added and completed in between show up in both.
+    changes.completed.extend(
+        changes.added.iter().filter(|t| t.completed).copied().collect::<Vec<_>>(),
+    );
+    changes.deleted = old_todos
+        .iter()
+        .filter(|t| !new_todos.iter().any(|n| n.id == t.id))
+        .collect();
+
+    let lists: Vec<&TodoList> = new_lists.iter().chain(old_lists.iter()).collect();
+    print_changes(&changes, &lists);
+    Ok(())
+}
+
+/// Changes in the current database since `since`, based on the created and
+/// completed dates. Deletions leave no trace so they can only be found by
+/// comparing files.
+pub fn diff_since(since: NaiveDate) -> Result<(), Box<dyn Error>> {
+    let todos = fetch_todos(&TodoQuery::new())?;
+    let lists = fetch_lists()?;
+
+    let changes = Changes {
+        added: todos.iter().filter(|t| t.created_date.is_some_and(|d| d >= since)).collect(),
+        completed: todos
+            .iter()
+            .filter(|t| t.completed && t.completed_at.is_some_and(|d| d.date() >= since))
+            .collect(),
+        deleted: vec![],
+    };
+    print_changes(&changes, &lists.iter().collect::<Vec<_>>());
+    Ok(())
+}
+
+fn print_changes(changes: &Changes, lists: &[&TodoList]) {
+    let sections = [
+        ("Added", "+", &changes.added),
+        ("Completed", "x", &changes.completed),
+        ("Deleted", "-", &changes.deleted),
+    ];
+    for (heading, marker, todos) in sections {
+        if todos.is_empty() {
+            continue;
+        }
+        println!("{} ({})", heading, todos.len());
+        for todo in todos.iter() {
+            let list = lists
+                .iter()
+                .find(|l| l.id == Some(todo.list_id))
+                .map(|l| l.title.as_str())
+                .unwrap_or("?");
+            println!("  {} {}\t{}\t{}", marker, todo.id.unwrap_or(0), list, todo.title);
+        }
+    }
+}
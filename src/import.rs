@@ -0,0 +1,152 @@
+use std::{collections::HashSet, error::Error, fs, path::Path};
+
+use chrono::Local;
+
+use crate::{
+    database::{add_attachments, add_list, add_todos, fetch_attachment_urls, fetch_lists},
+    model::{Todo, TodoList},
+    serialize::{deserialize, Format},
+};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ImportFormat {
+    /// The bookmarks.html export of Firefox, Chrome and Safari
+    NetscapeBookmarks,
+    /// A file written by `todo export --format json`
+    Json,
+    /// A file written by `todo export --format csv`
+    Csv,
+    /// A todo.txt file, e.g. written by `todo export --format todo-txt`
+    TodoTxt,
+    /// An iCalendar file of VTODOs, e.g. written by `todo export --format ics`
+    Ics,
+}
+
+pub struct Bookmark {
+    pub url: String,
+    pub title: String,
+}
+
+/// Imports `file` into the list titled `list_title` and returns how many todos
+/// were added.
+pub fn import(format: ImportFormat, file: &Path, list_title: &str) -> Result<usize, Box<dyn Error>> {
+    let content = fs::read_to_string(file)?;
+    let format = match format {
+        ImportFormat::NetscapeBookmarks => return import_bookmarks(&content, list_title),
+        ImportFormat::Json => Format::Json,
+        ImportFormat::Csv => Format::Csv,
+        ImportFormat::TodoTxt => Format::TodoTxt,
+        ImportFormat::Ics => Format::Ics,
+    };
+
+    // Ids, lists and milestones belong to the database the file was exported
+    // from.
+    let mut todos = deserialize(format, &content)?;
+    let list_id = find_or_create_list(list_title)?;
+    for todo in &mut todos {
+        todo.id = None;
+        todo.list_id = list_id;
+        todo.milestone_id = None;
+    }
+    add_todos(&todos)?;
+    Ok(todos.len())
+}
+
+fn import_bookmarks(content: &str, list_title: &str) -> Result<usize, Box<dyn Error>> {
+    let bookmarks = parse_netscape_bookmarks(content);
+    let list_id = find_or_create_list(list_title)?;
+    let mut known: HashSet<String> = fetch_attachment_urls()?.into_iter().collect();
+    let mut todos = vec![];
+    let mut urls = vec![];
+    for bookmark in bookmarks {
+        if !known.insert(bookmark.url.clone()) {
+            continue;
+        }
+        todos.push(Todo {
+            created_date: Some(Local::now().date_naive()),
+            ..Todo::new(list_id, bookmark.title)
+        });
+        urls.push(bookmark.url);
+    }
+
+    let ids = add_todos(&todos)?;
+    add_attachments(&ids.into_iter().zip(urls).collect::<Vec<_>>())?;
+    Ok(todos.len())
+}
+
+pub fn find_or_create_list(title: &str) -> Result<usize, Box<dyn Error>> {
+    let existing = fetch_lists()?.into_iter().find(|l| l.title == title);
+    Ok(match existing.and_then(|l| l.id) {
+        Some(id) => id,
+        None => add_list(&TodoList { id: None, title: title.to_string(), due_date: None })?,
+    })
+}
+
+/// Reads every `<A HREF="...">title</A>` entry. Folders are ignored.
+pub fn parse_netscape_bookmarks(html: &str) -> Vec<Bookmark> {
+    // ASCII lowercasing keeps the byte offsets valid for `html`.
+    let lower = html.to_ascii_lowercase();
+    let mut bookmarks = vec![];
+    let mut rest = 0;
+    while let Some(start) = lower[rest..].find("<a ").map(|i| i + rest) {
+        let Some(tag_end) = lower[start..].find('>').map(|i| i + start) else {
+            break;
+        };
+        let Some(close) = lower[tag_end..].find("</a>").map(|i| i + tag_end) else {
+            break;
+        };
+        rest = close;
+
+        let tag = &html[start..tag_end];
+        let Some(url) = attribute(tag, "href") else {
+            continue;
+        };
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            continue;
+        }
+        let title = decode_entities(html[tag_end + 1..close].trim());
+        bookmarks.push(Bookmark {
+            title: if title.is_empty() { url.clone() } else { title },
+            url,
+        });
+    }
+    bookmarks
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let start = lower.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = start + tag[start..].find('"')?;
+    Some(decode_entities(&tag[start..end]))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_bookmarks_after_non_ascii_text() {
+        let html = concat!(
+            "<DL><p>\n",
+            "<DT><H3>İstanbul \u{212A}elvin</H3>\n",
+            "<DT><A HREF=\"https://example.com/a?x=1&amp;y=2\" ADD_DATE=\"1\">İzmir &amp; co</A>\n",
+            "<DT><A HREF=\"ftp://example.com\">Skipped</A>\n",
+            "<DT><a href=\"http://example.com/b\"></a>\n",
+            "</DL><p>\n",
+        );
+        let bookmarks = parse_netscape_bookmarks(html);
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].url, "https://example.com/a?x=1&y=2");
+        assert_eq!(bookmarks[0].title, "İzmir & co");
+        assert_eq!(bookmarks[1].url, "http://example.com/b");
+        assert_eq!(bookmarks[1].title, "http://example.com/b");
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    env,
+    error::Error,
+    fmt, fs,
+    process::{Command, Output},
+    thread,
+    time::Duration,
+};
+
+use chrono::{Datelike, Days, Local, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::{config::Config, database::todo_dir};
+
+/// When a job runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    /// `daily HH:MM`
+    Daily(NaiveTime),
+    /// `weekly <day> HH:MM`, e.g. `weekly sun 03:00`
+    Weekly(Weekday, NaiveTime),
+}
+
+impl Schedule {
+    /// The latest time the job was meant to run at, `now` included.
+    fn last_at(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let (days_back, time) = match *self {
+            Schedule::Daily(time) => (0, time),
+            Schedule::Weekly(day, time) => {
+                let days = (7 + now.weekday().num_days_from_monday() - day.num_days_from_monday()) % 7;
+                (days as u64, time)
+            }
+        };
+        let at = (now.date() - Days::new(days_back)).and_time(time);
+        match at <= now {
+            true => at,
+            false => at - self.period(),
+        }
+    }
+
+    fn next_at(&self, now: NaiveDateTime) -> NaiveDateTime {
+        self.last_at(now) + self.period()
+    }
+
+    fn period(&self) -> chrono::Duration {
+        match self {
+            Schedule::Daily(_) => chrono::Duration::days(1),
+            Schedule::Weekly(..) => chrono::Duration::weeks(1),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Daily(time) => write!(f, "daily {}", time.format("%H:%M")),
+            Schedule::Weekly(day, time) => write!(f, "weekly {} {}", day, time.format("%H:%M")),
+        }
+    }
+}
+
+/// What a job runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// A `todo` subcommand with its arguments, e.g. `tidy --older-than 90`
+    Todo(Vec<String>),
+    /// `! command`, run by `sh -c`, e.g. `! todo report aging | mail -s Todos me`
+    Shell(String),
+}
+
+/// A `job.<name> = <schedule> <action>` line from the config, e.g.
+/// `job.backup = daily 02:00 backup`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    pub name: String,
+    pub schedule: Schedule,
+    pub action: Action,
+}
+
+impl Job {
+    pub fn parse(name: &str, value: &str) -> Option<Job> {
+        let (kind, rest) = next_word(value)?;
+        let (schedule, rest) = match kind {
+            "daily" => {
+                let (time, rest) = next_word(rest)?;
+                (Schedule::Daily(parse_time(time)?), rest)
+            }
+            "weekly" => {
+                let (day, rest) = next_word(rest)?;
+                let (time, rest) = next_word(rest)?;
+                (Schedule::Weekly(day.parse().ok()?, parse_time(time)?), rest)
+            }
+            _ => return None,
+        };
+        // The shell command is kept as written, quoting and all.
+        let action = match rest.trim().strip_prefix('!') {
+            Some(command) if !command.trim().is_empty() => Action::Shell(command.trim().to_string()),
+            Some(_) => return None,
+            None if rest.trim().is_empty() => return None,
+            None => Action::Todo(rest.split_whitespace().map(String::from).collect()),
+        };
+        Some(Job {
+            name: name.to_string(),
+            schedule,
+            action,
+        })
+    }
+
+    fn run(&self) -> std::io::Result<Output> {
+        match &self.action {
+            Action::Todo(args) => Command::new(env::current_exe()?).args(args).output(),
+            Action::Shell(command) => Command::new("sh").arg("-c").arg(command).output(),
+        }
+    }
+}
+
+/// The first word of `text` and what follows it.
+fn next_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    (end > 0).then(|| (&text[..end], &text[end..]))
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+/// How a job went the last time it ran.
+struct Run {
+    name: String,
+    at: NaiveDateTime,
+    ok: bool,
+    /// The last line the job printed, or the first of its error.
+    summary: String,
+}
+
+const STATUS_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// `~/.todo/jobs.status`, one tab separated line per job.
+fn read_status() -> Vec<Run> {
+    let content = fs::read_to_string(todo_dir().join("jobs.status")).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            Some(Run {
+                name: fields.next()?.to_string(),
+                at: NaiveDateTime::parse_from_str(fields.next()?, STATUS_FORMAT).ok()?,
+                ok: fields.next()? == "ok",
+                summary: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn write_status(runs: &[Run]) -> std::io::Result<()> {
+    let lines: String = runs
+        .iter()
+        .map(|run| {
+            let ok = if run.ok { "ok" } else { "failed" };
+            format!("{}\t{}\t{}\t{}\n", run.name, run.at.format(STATUS_FORMAT), ok, run.summary)
+        })
+        .collect();
+    fs::write(todo_dir().join("jobs.status"), lines)
+}
+
+fn summarize(output: &std::io::Result<Output>) -> (bool, String) {
+    let (ok, text) = match output {
+        Ok(output) if output.status.success() => (true, String::from_utf8_lossy(&output.stdout).to_string()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            match stderr.trim().is_empty() {
+                true => (false, output.status.to_string()),
+                false => (false, stderr),
+            }
+        }
+        Err(e) => (false, e.to_string()),
+    };
+    let line = match ok {
+        true => text.lines().rev().find(|line| !line.trim().is_empty()),
+        false => text.lines().find(|line| !line.trim().is_empty()),
+    };
+    (ok, line.unwrap_or("done").trim().replace('\t', " "))
+}
+
+/// Seconds between checks for due jobs.
+const CHECK_EVERY: u64 = 30;
+
+/// Runs the jobs from the config when they are due, until killed. A run of
+/// a job that ran before, missed while the daemon wasn't running, is made up
+/// once on start. The config is read again before every check, so changes
+/// apply without a restart.
+pub fn run_daemon() -> Result<(), Box<dyn Error>> {
+    println!("Running scheduled jobs, see todo daemon status");
+    // When each job last ran, or was first seen.
+    let mut seen: Vec<(String, NaiveDateTime)> = vec![];
+    loop {
+        let now = Local::now().naive_local();
+        let mut runs = read_status();
+        let mut ran = false;
+        for job in Config::load().jobs {
+            let last = runs.iter().find(|run| run.name == job.name).map(|run| run.at);
+            let since = match (last, seen.iter().find(|(name, _)| *name == job.name)) {
+                (Some(last), _) => last,
+                (None, Some((_, since))) => *since,
+                // A new job waits for its first scheduled time instead of
+                // running right away, e.g. a weekly tidy added on a Wednesday.
+                (None, None) => {
+                    seen.push((job.name.clone(), job.schedule.last_at(now)));
+                    continue;
+                }
+            };
+            if !is_due(&job.schedule, since, now) {
+                continue;
+            }
+            let (ok, summary) = summarize(&job.run());
+            println!("{} {}: {}", job.name, if ok { "ran" } else { "failed" }, summary);
+            runs.retain(|run| run.name != job.name);
+            runs.push(Run {
+                name: job.name.clone(),
+                at: now,
+                ok,
+                summary,
+            });
+            ran = true;
+        }
+        if ran {
+            write_status(&runs)?;
+        }
+        thread::sleep(Duration::from_secs(CHECK_EVERY));
+    }
+}
+
+/// Whether a scheduled time passed after `since`, the job's last run.
+fn is_due(schedule: &Schedule, since: NaiveDateTime, now: NaiveDateTime) -> bool {
+    since < schedule.last_at(now)
+}
+
+/// Prints every configured job with its last result and next run.
+pub fn print_status() -> Result<(), Box<dyn Error>> {
+    let jobs = Config::load().jobs;
+    if jobs.is_empty() {
+        println!("No jobs configured, add e.g. job.backup = daily 02:00 backup to ~/.todo/config");
+        return Ok(());
+    }
+    let now = Local::now().naive_local();
+    let runs = read_status();
+    for job in &jobs {
+        println!("{} ({}), next {}", job.name, job.schedule, job.schedule.next_at(now).format("%Y-%m-%d %H:%M"));
+        match runs.iter().find(|run| run.name == job.name) {
+            Some(run) => {
+                let result = if run.ok { "ok" } else { "failed" };
+                println!("  last run {} {}: {}", run.at.format("%Y-%m-%d %H:%M"), result, run.summary);
+            }
+            None => println!("  not run yet"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap().and_time(parse_time(time).unwrap())
+    }
+
+    #[test]
+    fn parses_schedules_and_actions() {
+        let job = Job::parse("tidy", "weekly sun 03:00 tidy --older-than 90").unwrap();
+        assert_eq!(job.name, "tidy");
+        assert_eq!(job.schedule, Schedule::Weekly(Weekday::Sun, parse_time("03:00").unwrap()));
+        assert_eq!(job.action, Action::Todo(vec!["tidy".into(), "--older-than".into(), "90".into()]));
+
+        let job = Job::parse("digest", "daily 08:00 ! todo report aging | mail -s 'My  todos' me").unwrap();
+        assert_eq!(job.schedule, Schedule::Daily(parse_time("08:00").unwrap()));
+        assert_eq!(job.action, Action::Shell("todo report aging | mail -s 'My  todos' me".into()));
+        assert_eq!(job.schedule.to_string(), "daily 08:00");
+
+        assert_eq!(Job::parse("x", "daily 08:00"), None);
+        assert_eq!(Job::parse("x", "daily 08:00 !"), None);
+        assert_eq!(Job::parse("x", "daily 8pm backup"), None);
+        assert_eq!(Job::parse("x", "weekly someday 03:00 backup"), None);
+        assert_eq!(Job::parse("x", "hourly backup"), None);
+        assert_eq!(Job::parse("x", ""), None);
+    }
+
+    #[test]
+    fn finds_the_last_and_next_run() {
+        let daily = Job::parse("x", "daily 08:00 backup").unwrap().schedule;
+        // Later today, so yesterday's was the last.
+        assert_eq!(daily.last_at(at("2026-10-14", "07:59")), at("2026-10-13", "08:00"));
+        assert_eq!(daily.next_at(at("2026-10-14", "07:59")), at("2026-10-14", "08:00"));
+        assert_eq!(daily.last_at(at("2026-10-14", "08:00")), at("2026-10-14", "08:00"));
+
+        // 2026-10-14 is a Wednesday.
+        let weekly = Job::parse("x", "weekly sun 03:00 tidy").unwrap().schedule;
+        assert_eq!(weekly.last_at(at("2026-10-14", "12:00")), at("2026-10-11", "03:00"));
+        assert_eq!(weekly.next_at(at("2026-10-14", "12:00")), at("2026-10-18", "03:00"));
+        // On the day itself, before and after the time.
+        assert_eq!(weekly.last_at(at("2026-10-18", "02:00")), at("2026-10-11", "03:00"));
+        assert_eq!(weekly.last_at(at("2026-10-18", "04:00")), at("2026-10-18", "03:00"));
+        assert_eq!(weekly.next_at(at("2026-10-18", "04:00")), at("2026-10-25", "03:00"));
+    }
+
+    #[test]
+    fn is_due_once_a_scheduled_time_passed() {
+        let weekly = Job::parse("x", "weekly sun 03:00 tidy").unwrap().schedule;
+        let since = weekly.last_at(at("2026-10-14", "12:00"));
+        assert!(!is_due(&weekly, since, at("2026-10-17", "23:00")));
+        assert!(is_due(&weekly, since, at("2026-10-18", "03:00")));
+        assert!(!is_due(&weekly, at("2026-10-18", "03:00"), at("2026-10-18", "03:30")));
+    }
+}
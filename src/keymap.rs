@@ -0,0 +1,351 @@
+use crossterm::event::KeyCode::{self, Backspace, Char, Down, Enter, Esc, Up};
+
+use crate::{AppState, InputField, State};
+
+/// What a key does. The TUI looks every key up with `action`, so the hint bar
+/// built from the same bindings can't promise keys that do something else.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Down,
+    Up,
+    OpenList,
+    ToLists,
+    Complete,
+    ToggleDetails,
+    FollowLink,
+    JumpBack,
+    NewTodo,
+    NewList,
+    Edit,
+    Delete,
+    UndoDelete,
+    Link,
+    CyclePriority,
+    SetMilestone,
+    Fold,
+    UnfoldAll,
+    MilestoneEarlier,
+    MilestoneLater,
+    FilterRelated,
+    Stats,
+    Today,
+    Timeline,
+    Workspaces,
+    Tags,
+    /// Handled by `Macros::handle` before the lookup, listed for the hints.
+    Record,
+    /// Handled by `Macros::handle` before the lookup, listed for the hints.
+    Replay,
+    Quit,
+    EditTitle,
+    EditDescription,
+    EditDueDate,
+    EditSlot,
+    Save,
+    Cancel,
+    NextField,
+    DeleteChar,
+    StopEditing,
+    Back,
+    Switch,
+    Rename,
+    Merge,
+    Recolor,
+    RemoveTag,
+}
+
+/// Keys doing the same and what they do, shown as `(keys) label`. Adjacent
+/// bindings with the same label share it, e.g. `(j,k) move`.
+pub struct Binding {
+    pub keys: &'static [KeyCode],
+    pub action: Action,
+    pub label: &'static str,
+}
+
+const fn bind(keys: &'static [KeyCode], action: Action, label: &'static str) -> Binding {
+    Binding { keys, action, label }
+}
+
+/// The part of the TUI keys are handled by, each with its own bindings.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Lists,
+    Todos,
+    Details,
+    Form,
+    ListForm,
+    Field,
+    Prompt,
+    Stats,
+    Today,
+    Timeline,
+    Workspaces,
+    Tags,
+}
+
+const LISTS: &[Binding] = &[
+    bind(&[Char('u')], Action::UndoDelete, "undo delete"),
+    bind(&[Char('j'), Down], Action::Down, "move"),
+    bind(&[Char('k'), Up], Action::Up, "move"),
+    bind(&[Char('l')], Action::OpenList, "open list"),
+    bind(&[Char('N')], Action::NewTodo, "new todo"),
+    bind(&[Char('L')], Action::NewList, "new list"),
+    bind(&[Char('E')], Action::Edit, "edit list"),
+    bind(&[Char('D')], Action::Delete, "delete list"),
+    bind(&[Char('S')], Action::Stats, "stats"),
+    bind(&[Char('T')], Action::Today, "today"),
+    bind(&[Char('G')], Action::Timeline, "timeline"),
+    bind(&[Char('W')], Action::Workspaces, "workspace"),
+    bind(&[Char('#')], Action::Tags, "tags"),
+    bind(&[Char('m')], Action::Record, "record"),
+    bind(&[Char('@')], Action::Replay, "replay"),
+    bind(&[Char('q')], Action::Quit, "quit"),
+];
+
+const TODOS: &[Binding] = &[
+    bind(&[Char('u')], Action::UndoDelete, "undo delete"),
+    bind(&[Char('j'), Down], Action::Down, "move"),
+    bind(&[Char('k'), Up], Action::Up, "move"),
+    bind(&[Char('h')], Action::ToLists, "lists"),
+    bind(&[Char(' '), Char('l')], Action::Complete, "complete"),
+    bind(&[Char('v')], Action::ToggleDetails, "details"),
+    bind(&[Char('N')], Action::NewTodo, "new todo"),
+    bind(&[Char('E')], Action::Edit, "edit"),
+    bind(&[Char('D')], Action::Delete, "delete"),
+    bind(&[Char('R')], Action::Link, "link"),
+    bind(&[Char('P')], Action::CyclePriority, "priority"),
+    bind(&[Char('M')], Action::SetMilestone, "milestone"),
+    bind(&[Char('z')], Action::Fold, "fold"),
+    bind(&[Char('Z')], Action::UnfoldAll, "fold"),
+    bind(&[Char('[')], Action::MilestoneEarlier, "move milestone"),
+    bind(&[Char(']')], Action::MilestoneLater, "move milestone"),
+    bind(&[Char('f')], Action::FilterRelated, "related"),
+    bind(&[Char('L')], Action::NewList, "new list"),
+    bind(&[Char('S')], Action::Stats, "stats"),
+    bind(&[Char('T')], Action::Today, "today"),
+    bind(&[Char('G')], Action::Timeline, "timeline"),
+    bind(&[Char('W')], Action::Workspaces, "workspace"),
+    bind(&[Char('#')], Action::Tags, "tags"),
+    bind(&[Char('m')], Action::Record, "record"),
+    bind(&[Char('@')], Action::Replay, "replay"),
+    bind(&[Char('q')], Action::Quit, "quit"),
+];
+
+/// The todos pane with the selected todo's details open: the same keys, and
+/// links to follow.
+const DETAILS: &[Binding] = &[
+    bind(&[Char('v')], Action::ToggleDetails, "close details"),
+    bind(&[Char('g')], Action::FollowLink, "follow link"),
+    bind(&[Char('b')], Action::JumpBack, "jump back"),
+    bind(&[Char('u')], Action::UndoDelete, "undo delete"),
+    bind(&[Char('j'), Down], Action::Down, "move"),
+    bind(&[Char('k'), Up], Action::Up, "move"),
+    bind(&[Char('h')], Action::ToLists, "lists"),
+    bind(&[Char(' '), Char('l')], Action::Complete, "complete"),
+    bind(&[Char('N')], Action::NewTodo, "new todo"),
+    bind(&[Char('E')], Action::Edit, "edit"),
+    bind(&[Char('D')], Action::Delete, "delete"),
+    bind(&[Char('R')], Action::Link, "link"),
+    bind(&[Char('P')], Action::CyclePriority, "priority"),
+    bind(&[Char('M')], Action::SetMilestone, "milestone"),
+    bind(&[Char('z')], Action::Fold, "fold"),
+    bind(&[Char('Z')], Action::UnfoldAll, "fold"),
+    bind(&[Char('[')], Action::MilestoneEarlier, "move milestone"),
+    bind(&[Char(']')], Action::MilestoneLater, "move milestone"),
+    bind(&[Char('f')], Action::FilterRelated, "related"),
+    bind(&[Char('L')], Action::NewList, "new list"),
+    bind(&[Char('S')], Action::Stats, "stats"),
+    bind(&[Char('T')], Action::Today, "today"),
+    bind(&[Char('G')], Action::Timeline, "timeline"),
+    bind(&[Char('W')], Action::Workspaces, "workspace"),
+    bind(&[Char('#')], Action::Tags, "tags"),
+    bind(&[Char('m')], Action::Record, "record"),
+    bind(&[Char('@')], Action::Replay, "replay"),
+    bind(&[Char('q')], Action::Quit, "quit"),
+];
+
+const FORM: &[Binding] = &[
+    bind(&[Char('t')], Action::EditTitle, "title"),
+    bind(&[Char('d')], Action::EditDescription, "description"),
+    bind(&[Char('D')], Action::EditDueDate, "due date"),
+    bind(&[Char('T')], Action::EditSlot, "time of day"),
+    bind(&[Char('s')], Action::Save, "save"),
+    bind(&[Esc, Char('q')], Action::Cancel, "cancel"),
+];
+
+const LIST_FORM: &[Binding] = &[
+    bind(&[Char('t')], Action::EditTitle, "title"),
+    bind(&[Char('D')], Action::EditDueDate, "due date"),
+    bind(&[Char('s')], Action::Save, "save"),
+    bind(&[Esc, Char('q')], Action::Cancel, "cancel"),
+];
+
+/// Other keys are typed into the field.
+const FIELD: &[Binding] = &[
+    bind(&[Enter], Action::NextField, "next field"),
+    bind(&[Backspace], Action::DeleteChar, "delete"),
+    bind(&[Esc], Action::StopEditing, "stop editing"),
+];
+
+/// Other keys are typed into the prompt.
+const PROMPT: &[Binding] = &[
+    bind(&[Enter], Action::Save, "save"),
+    bind(&[Backspace], Action::DeleteChar, "delete"),
+    bind(&[Esc], Action::Cancel, "cancel"),
+];
+
+const STATS: &[Binding] = &[bind(&[Char('S'), Esc, Char('q')], Action::Back, "back")];
+
+const TODAY: &[Binding] = &[bind(&[Char('T'), Esc, Char('q')], Action::Back, "back")];
+
+const TIMELINE: &[Binding] = &[bind(&[Char('G'), Esc, Char('q')], Action::Back, "back")];
+
+const WORKSPACES: &[Binding] = &[
+    bind(&[Char('j'), Down], Action::Down, "move"),
+    bind(&[Char('k'), Up], Action::Up, "move"),
+    bind(&[Enter], Action::Switch, "switch"),
+    bind(&[Char('W'), Esc, Char('q')], Action::Back, "back"),
+];
+
+const TAGS: &[Binding] = &[
+    bind(&[Char('j'), Down], Action::Down, "move"),
+    bind(&[Char('k'), Up], Action::Up, "move"),
+    bind(&[Char('r')], Action::Rename, "rename"),
+    bind(&[Char('m')], Action::Merge, "merge into"),
+    bind(&[Char('c')], Action::Recolor, "color"),
+    bind(&[Char('D')], Action::RemoveTag, "remove from all todos"),
+    bind(&[Char('#'), Esc, Char('q')], Action::Back, "back"),
+];
+
+/// The built-in bindings of a mode.
+pub fn bindings(mode: Mode) -> &'static [Binding] {
+    match mode {
+        Mode::Lists => LISTS,
+        Mode::Todos => TODOS,
+        Mode::Details => DETAILS,
+        Mode::Form => FORM,
+        Mode::ListForm => LIST_FORM,
+        Mode::Field => FIELD,
+        Mode::Prompt => PROMPT,
+        Mode::Stats => STATS,
+        Mode::Today => TODAY,
+        Mode::Timeline => TIMELINE,
+        Mode::Workspaces => WORKSPACES,
+        Mode::Tags => TAGS,
+    }
+}
+
+/// What `key` does in `mode`, `None` for keys without a binding, which the
+/// text fields type and the list modes offer to plugins.
+pub fn action(mode: Mode, key: KeyCode) -> Option<Action> {
+    bindings(mode).iter().find(|b| b.keys.contains(&key)).map(|b| b.action)
+}
+
+/// The mode keys are handled in right now, `None` while onboarding, which
+/// explains its keys as it goes.
+pub fn mode(state: &State) -> Option<Mode> {
+    Some(match state.state {
+        AppState::List(Some(_)) => Mode::Details,
+        AppState::List(None) if state.selecting_list => Mode::Lists,
+        AppState::List(None) => Mode::Todos,
+        AppState::Create(Some(_), _) | AppState::CreateList(Some(_), _) => Mode::Field,
+        AppState::Create(None, _) => Mode::Form,
+        AppState::CreateList(None, _) => Mode::ListForm,
+        AppState::Link(_) | AppState::Milestone(_) | AppState::TagEdit(..) => Mode::Prompt,
+        AppState::Stats => Mode::Stats,
+        AppState::Today => Mode::Today,
+        AppState::Timeline => Mode::Timeline,
+        AppState::Workspaces(_) => Mode::Workspaces,
+        AppState::Tags(_) => Mode::Tags,
+        AppState::Onboarding(_) => return None,
+    })
+}
+
+/// How a key is written in the hints. Arrow keys work alongside j and k
+/// without taking room in them.
+fn key_name(key: &KeyCode) -> Option<String> {
+    match key {
+        Char(' ') => Some("space".to_string()),
+        Char(c) => Some(c.to_string()),
+        Enter => Some("enter".to_string()),
+        Esc => Some("esc".to_string()),
+        Backspace => Some("backspace".to_string()),
+        _ => None,
+    }
+}
+
+/// The hint bar for the current mode: its bindings, the undo key only while
+/// a delete can be undone, then the keys of script plugins and those bound
+/// to executables in the config.
+pub fn hint(state: &State) -> String {
+    let Some(mode) = mode(state) else {
+        return String::new();
+    };
+    let mut hints: Vec<(Vec<String>, &str)> = vec![];
+    for binding in bindings(mode) {
+        if binding.action == Action::UndoDelete && state.pending_delete.is_none() {
+            continue;
+        }
+        // Enter on the last field of a form finishes it.
+        let label = match (binding.action, &state.state) {
+            (Action::NextField, AppState::Create(Some(InputField::Slot), _) | AppState::CreateList(..)) => "done",
+            _ => binding.label,
+        };
+        let keys = binding.keys.iter().filter_map(key_name);
+        match hints.last_mut() {
+            Some((previous, last)) if *last == label => previous.extend(keys),
+            _ => hints.push((keys.collect(), label)),
+        }
+    }
+    let mut hints: Vec<String> = hints.iter().map(|(keys, label)| format!("({}) {}", keys.join(","), label)).collect();
+    if matches!(mode, Mode::Lists | Mode::Todos | Mode::Details) {
+        hints.extend(state.plugins.keys().into_iter().map(|(key, label)| format!("({}) {}", key, label)));
+        let mut plugins: Vec<_> = state.config.key_actions.iter().collect();
+        plugins.sort();
+        hints.extend(plugins.into_iter().map(|(key, plugin)| format!("({}) {}", key, plugin)));
+    }
+    hints.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: &[Mode] = &[
+        Mode::Lists,
+        Mode::Todos,
+        Mode::Details,
+        Mode::Form,
+        Mode::ListForm,
+        Mode::Field,
+        Mode::Prompt,
+        Mode::Stats,
+        Mode::Today,
+        Mode::Timeline,
+        Mode::Workspaces,
+        Mode::Tags,
+    ];
+
+    #[test]
+    fn every_key_does_one_thing_per_mode() {
+        for &mode in MODES {
+            let keys: Vec<&KeyCode> = bindings(mode).iter().flat_map(|b| b.keys).collect();
+            for (index, key) in keys.iter().enumerate() {
+                assert!(!keys[..index].contains(key), "{:?} is bound twice in {:?}", key, mode);
+            }
+            for binding in bindings(mode) {
+                assert!(!binding.keys.is_empty(), "{:?} has no keys in {:?}", binding.action, mode);
+                for key in binding.keys {
+                    assert_eq!(action(mode, *key), Some(binding.action));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn text_fields_leave_letters_to_typing() {
+        for mode in [Mode::Field, Mode::Prompt] {
+            assert!(('a'..='z').chain('A'..='Z').all(|c| action(mode, Char(c)).is_none()));
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::{error::Error, fs, process::Command};
+
+use chrono::{Local, NaiveDate};
+
+use crate::{
+    config::Config,
+    database::{add_list_with_todos, fetch_lists},
+    model::{Priority, Todo, TodoList},
+    quickadd::parse_due,
+};
+
+/// A shareable checklist, like a packing or release list, that becomes a
+/// real list when installed with `todo template install`.
+///
+/// A template is a TOML file. Only the part of TOML below is understood:
+/// `key = value` pairs with strings or whole numbers as values, and
+/// a `[[todo]]` table for every todo.
+///
+/// ```toml
+/// # The title of the created list
+/// name = "Travel"
+/// # Optional, when the list is due after installing, in days or as a date
+/// due = 14
+///
+/// [[todo]]
+/// title = "Book flights #travel"
+/// # All optional
+/// description = "Check the baggage allowance\nand the seats"
+/// due = 2
+/// priority = "high"
+/// milestone = "Before leaving"
+/// ```
+///
+/// Due dates take what `due:` takes in quick-add, so fixed dates work too,
+/// e.g. `due = "2026-12-24"`.
+#[derive(Debug, Default)]
+pub struct ListTemplate {
+    pub name: String,
+    pub due: Option<String>,
+    pub todos: Vec<TemplateTodo>,
+}
+
+#[derive(Debug, Default)]
+pub struct TemplateTodo {
+    pub title: String,
+    pub description: Option<String>,
+    pub due: Option<String>,
+    pub priority: Priority,
+    pub milestone: Option<String>,
+}
+
+#[derive(Debug)]
+enum Value {
+    String(String),
+    Integer(i64),
+}
+
+impl Value {
+    /// Every key takes text, numbers are read as their digits, e.g. `due = 3`.
+    fn into_string(self) -> String {
+        match self {
+            Value::String(s) => s,
+            Value::Integer(n) => n.to_string(),
+        }
+    }
+}
+
+/// Reads a template from a file or, with curl, from an http(s) URL.
+pub fn read(source: &str) -> Result<String, Box<dyn Error>> {
+    if !(source.starts_with("http://") || source.starts_with("https://")) {
+        return Ok(fs::read_to_string(source)?);
+    }
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", source])
+        .output()
+        .map_err(|e| format!("Could not run curl to download {}: {}", source, e))?;
+    match output.status.success() {
+        true => Ok(String::from_utf8(output.stdout)?),
+        false => Err(format!("Could not download {}: {}", source, String::from_utf8_lossy(&output.stderr).trim()).into()),
+    }
+}
+
+pub fn parse(content: &str) -> Result<ListTemplate, String> {
+    let mut template = ListTemplate::default();
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') {
+            match line {
+                "[[todo]]" => template.todos.push(TemplateTodo::default()),
+                _ => return Err(format!("Line {}: only [[todo]] tables are supported", number)),
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("Line {}: expected key = value", number));
+        };
+        let key = key.trim();
+        let value = parse_value(value.trim()).map_err(|e| format!("Line {}: {}", number, e))?;
+        match template.todos.last_mut() {
+            None => match key {
+                "name" => template.name = value.into_string(),
+                "due" => template.due = Some(value.into_string()),
+                _ => return Err(format!("Line {}: unknown key {}", number, key)),
+            },
+            Some(todo) => match key {
+                "title" => todo.title = value.into_string(),
+                "description" => todo.description = Some(value.into_string()),
+                "due" => todo.due = Some(value.into_string()),
+                "milestone" => todo.milestone = Some(value.into_string()),
+                "priority" => {
+                    let priority = value.into_string();
+                    todo.priority = Priority::parse(&priority)
+                        .ok_or_else(|| format!("Line {}: priority is normal, high or urgent", number))?;
+                }
+                _ => return Err(format!("Line {}: unknown key {}", number, key)),
+            },
+        }
+    }
+    if template.name.trim().is_empty() {
+        return Err("The template needs a name".to_string());
+    }
+    if let Some(position) = template.todos.iter().position(|todo| todo.title.trim().is_empty()) {
+        return Err(format!("Todo {} of the template needs a title", position + 1));
+    }
+    Ok(template)
+}
+
+/// Everything before a `#` outside of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        return match literal.strip_suffix('\'') {
+            Some(literal) if !literal.contains('\'') => Ok(Value::String(literal.to_string())),
+            _ => Err("unclosed string".to_string()),
+        };
+    }
+    if let Some(basic) = value.strip_prefix('"') {
+        let mut string = String::new();
+        let mut chars = basic.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if chars.as_str().is_empty() => return Ok(Value::String(string)),
+                '"' => return Err("text after the string".to_string()),
+                '\\' => string.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    other => return Err(format!("unsupported escape \\{}", other.unwrap_or(' '))),
+                }),
+                c => string.push(c),
+            }
+        }
+        return Err("unclosed string, strings spanning lines are not supported".to_string());
+    }
+    value
+        .replace('_', "")
+        .parse()
+        .map(Value::Integer)
+        .map_err(|_| format!("unsupported value {}", value))
+}
+
+/// Creates a list titled `title`, or after the template, holding the
+/// template's todos. Due dates count from today. Returns the list title and
+/// how many todos were added.
+pub fn install(template: ListTemplate, title: Option<String>) -> Result<(String, usize), Box<dyn Error>> {
+    let title = title.unwrap_or(template.name);
+    if fetch_lists()?.iter().any(|list| list.title == title) {
+        return Err(format!("A list named {} exists, pass --list to install under another name", title).into());
+    }
+    let today = Local::now().date_naive();
+    let config = Config::load();
+    let due = |due: &Option<String>| -> Result<Option<NaiveDate>, String> {
+        due.as_deref().map(|due| parse_due(due, today, &config)).transpose()
+    };
+
+    // Everything is checked before the list is created.
+    let list_due = due(&template.due)?;
+    let todo_dues = template.todos.iter().map(|todo| due(&todo.due)).collect::<Result<Vec<_>, _>>()?;
+
+    let list = TodoList {
+        id: None,
+        title: title.clone(),
+        due_date: list_due,
+    };
+    let mut todos = vec![];
+    for (todo, due_date) in template.todos.into_iter().zip(todo_dues) {
+        let milestone = todo.milestone;
+        let todo = Todo {
+            description: todo.description,
+            due_date,
+            priority: todo.priority,
+            created_date: Some(today),
+            // The list id is set to the new list's on adding it.
+            ..Todo::new(0, todo.title)
+        };
+        todos.push((todo, milestone));
+    }
+    add_list_with_todos(&list, &todos)?;
+    Ok((title, todos.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_todos_in_order() {
+        let template = parse(
+            "name = \"Travel\" # the list\n\
+             due = 1_4\n\
+             \n\
+             [[todo]]\n\
+             title = \"Book flights #travel\"\n\
+             priority = \"high\"\n\
+             [[todo]]\n\
+             title = 'Pack # everything'\n\
+             description = \"Socks\\n\\t\\\"warm\\\" \\\\ wool # not a comment\"\n\
+             due = 2\n\
+             milestone = \"Before leaving\"\n",
+        )
+        .unwrap();
+        assert_eq!(template.name, "Travel");
+        assert_eq!(template.due.as_deref(), Some("14"));
+        assert_eq!(template.todos.len(), 2);
+        assert_eq!(template.todos[0].title, "Book flights #travel");
+        assert_eq!(template.todos[0].priority, Priority::High);
+        assert_eq!(template.todos[0].due, None);
+        assert_eq!(template.todos[1].title, "Pack # everything");
+        assert_eq!(
+            template.todos[1].description.as_deref(),
+            Some("Socks\n\t\"warm\" \\ wool # not a comment")
+        );
+        assert_eq!(template.todos[1].due.as_deref(), Some("2"));
+        assert_eq!(template.todos[1].milestone.as_deref(), Some("Before leaving"));
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        let error = |content: &str| parse(content).unwrap_err();
+        assert_eq!(error("name = \"A\"\n[todo]"), "Line 2: only [[todo]] tables are supported");
+        assert_eq!(error("name"), "Line 1: expected key = value");
+        assert_eq!(error("name = \"A\"\ncolor = 1"), "Line 2: unknown key color");
+        assert_eq!(error("name = \"A\"\n[[todo]]\nname = \"B\""), "Line 3: unknown key name");
+        assert_eq!(error("name = \"A"), "Line 1: unclosed string, strings spanning lines are not supported");
+        assert_eq!(error("name = 'A"), "Line 1: unclosed string");
+        assert_eq!(error("name = \"A\" x"), "Line 1: text after the string");
+        assert_eq!(error("name = \"\\x\""), "Line 1: unsupported escape \\x");
+        assert_eq!(error("name = true"), "Line 1: unsupported value true");
+        assert_eq!(
+            error("name = \"A\"\n[[todo]]\ntitle = \"B\"\npriority = \"soon\""),
+            "Line 4: priority is normal, high or urgent"
+        );
+        assert_eq!(error("due = 3"), "The template needs a name");
+        assert_eq!(error("name = \"A\"\n[[todo]]\ntitle = \"B\"\n[[todo]]"), "Todo 2 of the template needs a title");
+    }
+}
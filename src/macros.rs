@@ -0,0 +1,122 @@
+use std::collections::{HashMap, VecDeque};
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+/// Largest replay count, more digits keep it there.
+const MAX_COUNT: usize = 999;
+
+enum Pending {
+    Record,
+    Replay,
+}
+
+/// Register style key macros: `m<reg>` starts recording into a register, `m`
+/// stops it again, and `[count]@<reg>` replays the recorded keys. Esc stops
+/// a replay that is still running.
+#[derive(Default)]
+pub struct Macros {
+    registers: HashMap<char, Vec<KeyEvent>>,
+    recording: Option<(char, Vec<KeyEvent>)>,
+    pending: Option<Pending>,
+    count: usize,
+    queue: VecDeque<KeyEvent>,
+}
+
+impl Macros {
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(register, _)| *register)
+    }
+
+    /// Next key of a running replay, if any.
+    pub fn next_replayed(&mut self) -> Option<KeyEvent> {
+        self.queue.pop_front()
+    }
+
+    pub fn replaying(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Drops the rest of a running replay.
+    pub fn stop_replay(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Adds a key to the macro being recorded.
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(key);
+        }
+    }
+
+    /// Handles the macro keys. Returns true if the key was consumed and should
+    /// not be dispatched as a normal action.
+    pub fn handle(&mut self, key: KeyEvent) -> bool {
+        if let Some(pending) = self.pending.take() {
+            if let KeyCode::Char(register) = key.code {
+                match pending {
+                    Pending::Record => self.recording = Some((register, vec![])),
+                    Pending::Replay => {
+                        if let Some(keys) = self.registers.get(&register) {
+                            for _ in 0..self.count.max(1) {
+                                self.queue.extend(keys.iter().copied());
+                            }
+                        }
+                    }
+                }
+            }
+            self.count = 0;
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char(c @ '0'..='9') if c != '0' || self.count > 0 => {
+                let digit = c.to_digit(10).expect("is a digit") as usize;
+                self.count = (self.count * 10 + digit).min(MAX_COUNT);
+                true
+            }
+            KeyCode::Char('m') => {
+                match self.recording.take() {
+                    Some((register, keys)) => {
+                        self.registers.insert(register, keys);
+                    }
+                    None => self.pending = Some(Pending::Record),
+                }
+                true
+            }
+            KeyCode::Char('@') => {
+                self.pending = Some(Pending::Replay);
+                true
+            }
+            _ => {
+                self.count = 0;
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn caps_the_replay_count() {
+        let mut macros = Macros::default();
+        for c in "mqjm".chars() {
+            if !macros.handle(key(c)) {
+                macros.record(key(c));
+            }
+        }
+        for c in "99999999999999999999999@q".chars() {
+            assert!(macros.handle(key(c)));
+        }
+        assert_eq!(macros.queue.len(), MAX_COUNT);
+        macros.stop_replay();
+        assert!(!macros.replaying());
+    }
+}
@@ -1,56 +1,233 @@
 use std::{
-    cmp::min,
+    collections::HashMap,
+    env,
     error::Error,
     io::{self, Stdout},
-    time::Duration,
+    mem,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
 };
 
-use chrono::{Local, Days, NaiveDate};
+use chrono::{Local, NaiveDate};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use database::{add_list, add_todo, delete_list, delete_todo, fetch_lists, toggle_todo_completion, update_todo};
-use model::{Todo, TodoList};
+use database::{
+    count_todos, fetch_attachments, fetch_field_values, fetch_fields, fetch_lists, fetch_milestones, fetch_relations,
+    fetch_tags, fetch_todo, retag,
+};
+use model::{Priority, Relation, TimeSlot, Todo, TodoList};
 use ratatui::{
     prelude::{Alignment, Constraint, CrosstermBackend, Direction, Layout},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, BorderType, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap},
     Terminal,
 };
 
-use crate::database::{fetch_incomplete_todos, fetch_todos};
+use crate::config::{Config, DEFAULT_WORKSPACE};
+use crate::cursor::Cursor;
+use crate::database::{fetch_todos, todo_dir, DatabaseError};
+use crate::import::ImportFormat;
+use crate::keymap::Action;
+use crate::macros::Macros;
+use crate::milestones::Header;
+use crate::plugin::Plugins;
+use crate::query::{Order, TodoQuery};
+use crate::queue::{Operation, Queue};
+use crate::rules::{ColorRule, Predicate};
+use crate::stats::{list_stats, quota_warning, ListStats, WINDOW_DAYS};
 
+mod cli;
+mod config;
+mod cursor;
+mod daemon;
 mod database;
+mod diff;
+mod import;
+mod jobs;
+mod keymap;
+mod list_template;
+mod macros;
+mod milestones;
 mod model;
+mod plugin;
+mod query;
+mod queue;
+mod quickadd;
+mod render;
+mod rules;
+mod serialize;
+mod stats;
+mod suggest;
+mod template;
+mod timeline;
+mod tutorial;
+mod url;
+
+use clap::{Parser, Subcommand};
+
+/// Steps of the first run wizard, shown when there are no lists yet.
+#[derive(Debug, Copy, Clone)]
+enum OnboardingStep {
+    CreateList,
+    Import,
+    Theme,
+    Keys,
+}
 
-use clap::Parser;
+/// Color rule presets offered during onboarding, see `rules::ColorRule`.
+const THEMES: [(&str, &[&str]); 3] = [
+    ("Classic: overdue todos in red", &["color = overdue -> red"]),
+    (
+        "Colorful: overdue red, due soon yellow, done dimmed",
+        &["color = overdue -> bold red", "color = due:<3d -> yellow", "color = completed -> dim"],
+    ),
+    ("Minimal: overdue todos in bold", &["color = overdue -> bold"]),
+];
 
 #[derive(Debug, Copy, Clone)]
 enum InputField {
     Title,
     Description,
     DueDate,
+    Slot,
+}
+
+/// What the prompt of the tags view changes about a tag.
+#[derive(Debug, Copy, Clone)]
+enum TagEdit {
+    Rename,
+    Merge,
+    Recolor,
 }
 
 enum AppState {
     List(Option<usize>),
     Create(Option<InputField>, Option<usize>),
-    CreateList(Option<InputField>),
+    CreateList(Option<InputField>, Option<usize>),
+    Stats,
+    Today,
+    Timeline,
+    Link(usize),
+    Milestone(usize),
+    Workspaces(usize),
+    Tags(usize),
+    TagEdit(usize, TagEdit),
+    Onboarding(OnboardingStep),
+}
+
+/// Seconds a delete can be undone before it happens.
+const UNDO_SECONDS: u64 = 5;
+
+/// What `D` deleted, kept from the database until it can't be undone.
+enum Deleted {
+    Todo(usize),
+    List(usize),
+}
+
+struct PendingDelete {
+    deleted: Deleted,
+    title: String,
+    since: Instant,
 }
 
 struct State {
     pub list_title: String,
+    pub list_due_date: Option<NaiveDate>,
     pub todo_description: String,
     pub todo_title: String,
     pub todo_due_date: Option<NaiveDate>,
+    pub todo_slot: Option<TimeSlot>,
     pub state: AppState,
     pub input: String,
-    pub lists_list_state: ListState,
-    pub todo_list_state: ListState,
+    pub lists_cursor: Cursor,
+    pub todos_cursor: Cursor,
     pub selecting_list: bool,
+    pub macros: Macros,
+    pub config: Config,
+    pub jump_history: Vec<usize>,
+    /// The date everything date dependent is computed against, moved forward
+    /// when the day changes while the app is open.
+    pub today: NaiveDate,
+    /// One line of feedback shown in the header until the next key press.
+    pub message: Option<String>,
+    /// Current step of `todo tutorial`, see `tutorial::STEPS`.
+    pub tutorial: Option<usize>,
+    pub filter: Option<QuickFilter>,
+    /// The name of the workspace whose database is in use.
+    pub workspace: String,
+    /// Where the other workspaces were left, to return to them as they were.
+    pub workspace_views: HashMap<String, WorkspaceView>,
+    /// While the TUI is locked, the part of the PIN typed so far.
+    pub locked: Option<String>,
+    /// Shown below the header while the configured quotas are exceeded.
+    pub quota_warning: Option<String>,
+    /// Hidden from the list view, deleted once the undo time is up.
+    pub pending_delete: Option<PendingDelete>,
+    /// Changes waiting to be retried after the database refused them.
+    pub queue: Queue,
+    /// Script plugins, for the keys they bind.
+    pub plugins: Plugins,
+}
+
+/// The parts of `State` that belong to one workspace's database.
+struct WorkspaceView {
+    lists_cursor: Cursor,
+    todos_cursor: Cursor,
+    selecting_list: bool,
+    jump_history: Vec<usize>,
+    filter: Option<QuickFilter>,
+}
+
+impl Default for WorkspaceView {
+    fn default() -> WorkspaceView {
+        WorkspaceView {
+            lists_cursor: Cursor::default(),
+            todos_cursor: Cursor::default(),
+            selecting_list: true,
+            jump_history: vec![],
+            filter: None,
+        }
+    }
+}
+
+/// Narrows the todos pane of one list down to the todos related to the one
+/// it was made from: those sharing a tag with it, or when it has none, its
+/// milestone.
+struct QuickFilter {
+    list_id: usize,
+    tags: Vec<String>,
+    milestone_id: Option<usize>,
+    /// Shown in the pane title while the filter is on.
+    label: String,
+}
+
+impl QuickFilter {
+    fn from_todo(todo: &Todo, headers: &[Header]) -> Option<QuickFilter> {
+        let tags = todo.tags();
+        let label = match (tags.is_empty(), milestones::position_of(headers, todo)) {
+            (false, _) => tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" "),
+            (true, Some(position)) => headers[position].milestone.title.clone(),
+            (true, None) => return None,
+        };
+        Some(QuickFilter {
+            list_id: todo.list_id,
+            milestone_id: todo.milestone_id.filter(|_| tags.is_empty()),
+            tags,
+            label,
+        })
+    }
+
+    fn matches(&self, todo: &Todo) -> bool {
+        match self.milestone_id {
+            Some(milestone_id) => todo.milestone_id == Some(milestone_id),
+            None => self.tags.iter().any(|tag| todo.has_tag(tag)),
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -63,42 +240,496 @@ struct Args {
     /// Only return amount of incomplete todos
     #[clap(short, long)]
     count: bool,
+
+    /// With --date, print aligned columns with list, tags and descriptions
+    #[arg(short, long)]
+    long: bool,
+
+    /// With --date, print the todos under a heading per group
+    #[arg(long, value_enum)]
+    group_by: Option<cli::GroupBy>,
+
+    /// Use the database of a workspace from the config instead of the default
+    /// one, or the one in $TODO_WORKSPACE
+    #[arg(short, long, global = true)]
+    workspace: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print incomplete todos one per line, for piping into fzf or skim
+    Pick,
+    /// Print the most urgent incomplete todos first, as scored by the
+    /// priority, the due date and the urgency hooks of plugins
+    Next {
+        /// How many todos to print
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Mark todos as completed
+    Done {
+        /// Ids of the todos to complete
+        ids: Vec<usize>,
+
+        /// Read lines printed by `pick` from stdin and complete those todos
+        #[arg(long)]
+        from_line: bool,
+    },
+    /// Import todos from another tool
+    Import {
+        /// Format of the file to import
+        #[arg(long, value_enum)]
+        format: ImportFormat,
+
+        /// List to import into, created if missing
+        #[arg(long, default_value = "Reading")]
+        list: String,
+
+        file: PathBuf,
+    },
+    /// Print every todo in a format `import` reads back
+    Export {
+        #[arg(long, value_enum)]
+        format: serialize::Format,
+
+        /// Only export todos in this list
+        #[arg(long)]
+        list: Option<String>,
+    },
+    /// Add a todo using quick-add syntax, e.g. `todo add Call mom @Home due:+1 at:evening`
+    Add {
+        #[arg(required_unless_present = "from_stdin", conflicts_with = "from_stdin")]
+        text: Vec<String>,
+
+        /// List for todos that don't name one with @List, created if missing.
+        /// Without it the list is guessed from the words of earlier todos
+        #[arg(long)]
+        list: Option<String>,
+
+        /// Put todos that don't name a list in the Inbox instead of guessing
+        #[arg(long, conflicts_with = "list")]
+        no_suggest: bool,
+
+        /// Add a todo for every line piped in, e.g. meeting notes
+        #[arg(long)]
+        from_stdin: bool,
+
+        /// Open the TUI on the list the todos were added to afterwards
+        #[arg(long)]
+        open: bool,
+    },
+    /// Print the todos changed since a cursor as JSON, with the ids of
+    /// deleted todos and the cursor to pass next time
+    Changes {
+        /// Cursor printed by the previous call, 0 for everything
+        #[arg(long, default_value_t = 0)]
+        since: i64,
+    },
+    /// Serve a read-only, self-refreshing HTML page of a list, e.g. for
+    /// glancing at the shopping list from a phone on the same network
+    Publish {
+        #[arg(long)]
+        list: String,
+
+        /// Address to listen on, 0.0.0.0 to reach the page from other devices
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Add a todo for every quick-add line written to a unix socket
+    Listen {
+        /// Defaults to ~/.todo/todo.sock
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Print todos using a template, e.g. `--template '{{list}}: {{title}}\n'`
+    List {
+        /// Only print todos in this list
+        #[arg(long)]
+        list: Option<String>,
+
+        /// Include completed todos
+        #[arg(long)]
+        all: bool,
+
+        /// Only print todos with this #tag, can be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Only print todos whose custom field has this value, can be repeated
+        #[arg(long = "field", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+        fields: Vec<(String, String)>,
+
+        /// Only print todos whose title or description contains this text
+        #[arg(long)]
+        search: Option<String>,
+
+        /// Only print todos due on or after this date
+        #[arg(long)]
+        due_from: Option<NaiveDate>,
+
+        /// Only print todos due on or before this date
+        #[arg(long)]
+        due_to: Option<NaiveDate>,
+
+        /// Print at most this many todos
+        #[arg(long)]
+        limit: Option<usize>,
+
+        #[arg(long, value_enum, default_value_t)]
+        sort: Order,
+
+        /// Template rendered for every todo. Fields: id, title, description,
+        /// due, slot, created, completed, completed_at, priority, list
+        #[arg(
+            long,
+            default_value = "{{id}}\\t{{list}}\\t{{due}}\\t{{title}}\\n",
+            conflicts_with = "template_file",
+            allow_hyphen_values = true
+        )]
+        template: String,
+
+        /// Read the todo template from a file
+        #[arg(long)]
+        template_file: Option<PathBuf>,
+
+        /// Template printed before the todos. Fields: count
+        #[arg(long, allow_hyphen_values = true)]
+        header: Option<String>,
+
+        /// Template printed after the todos. Fields: count
+        #[arg(long, allow_hyphen_values = true)]
+        footer: Option<String>,
+    },
+    /// Show todos added, completed and deleted between two database files,
+    /// or added and completed since a date
+    Diff {
+        /// Older copy of the database
+        #[arg(required_unless_present = "since", requires = "new")]
+        old: Option<PathBuf>,
+
+        /// Newer copy of the database
+        new: Option<PathBuf>,
+
+        /// Compare the current database against this date instead
+        #[arg(long, conflicts_with = "old")]
+        since: Option<NaiveDate>,
+    },
+    /// List or run plugins from ~/.todo/plugins
+    Plugin {
+        #[command(subcommand)]
+        command: PluginCommand,
+    },
+    /// Set or remove custom fields of a todo, e.g. `todo edit 42 --set customer=ACME`.
+    /// A `start=YYYY-MM-DD` field is where the todo's span begins on the timeline
+    Edit {
+        id: usize,
+
+        /// Set a field, can be repeated
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+        set: Vec<(String, String)>,
+
+        /// Remove a field, can be repeated
+        #[arg(long, value_name = "KEY")]
+        unset: Vec<String>,
+    },
+    /// Print everything about one todo: its description, fields,
+    /// attachments and linked todos
+    Show {
+        id: usize,
+
+        /// Print a JSON object instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Go through todos due today or earlier one at a time, answering
+    /// d(one), s(nooze), k(eep) or q(uit) on a plain line
+    Triage,
+    /// Raise the priority of long overdue todos, meant to run daily from
+    /// cron. The TUI does the same on start when escalate_after is set
+    Escalate {
+        /// Days overdue before a todo becomes high priority, urgent after
+        /// twice as long. Defaults to escalate_after from the config
+        #[arg(long)]
+        after: Option<u64>,
+    },
+    /// Delete todos completed long ago, to keep the database small
+    Tidy {
+        /// Delete todos completed more than this many days ago
+        #[arg(long, default_value_t = 90)]
+        older_than: u64,
+    },
+    /// Copy the database to ~/.todo/backups, keeping the newest copies
+    Backup {
+        /// How many backups to keep
+        #[arg(long, default_value_t = 7)]
+        keep: usize,
+    },
+    /// Share checklists as list templates
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommand,
+    },
+    /// Print reports about the todos
+    Report {
+        #[command(subcommand)]
+        command: ReportCommand,
+    },
+    /// Learn the basics step by step in a throwaway database
+    Tutorial,
+    /// Manage the background services
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PluginCommand {
+    /// List installed plugins
+    List,
+    /// Run a plugin as a custom command
+    Run {
+        name: String,
+
+        /// Arguments passed on to the plugin
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TemplateCommand {
+    /// Create a list from a TOML list template, a file or an http(s) URL
+    Install {
+        source: String,
+
+        /// Title of the created list, defaults to the template's name
+        #[arg(long)]
+        list: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ReportCommand {
+    /// Count incomplete todos per list by how long ago they were created,
+    /// listing the oldest ones
+    Aging {
+        /// How many of the oldest todos to list per list
+        #[arg(long, default_value_t = 3)]
+        oldest: usize,
+
+        /// Print a JSON object instead
+        #[arg(long)]
+        json: bool,
+    },
 }
+
+#[derive(Subcommand, Debug)]
+enum DaemonCommand {
+    /// Write systemd user units (Linux) or launchd agents (macOS) for the daemons
+    Install,
+    /// Run the scheduled jobs from the config (job.<name> = ...) as they
+    /// come due
+    Run,
+    /// Show when each scheduled job last ran, how it went and when it runs next
+    Status,
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
 
     let args: Args = Args::parse(); 
+    // Set for plugins, so their calls back into todo use the same database.
+    let workspace = args
+        .workspace
+        .or_else(|| env::var("TODO_WORKSPACE").ok())
+        .unwrap_or_else(|| DEFAULT_WORKSPACE.to_string());
+    match Config::load().workspace(&workspace) {
+        Some(path) => database::use_database(path),
+        None => return Err(format!("No workspace named {}, add workspace.{} = <path> to the config", workspace, workspace).into()),
+    }
+    if let Some(command) = args.command {
+        return match command {
+            Command::Pick => cli::pick(),
+            Command::Next { limit } => cli::next(limit),
+            Command::Done { ids, from_line } => cli::done(ids, from_line),
+            Command::Edit { id, set, unset } => cli::edit(id, set, unset),
+            Command::Show { id, json } => cli::show(id, json),
+            Command::Triage => cli::triage(),
+            Command::Escalate { after } => cli::escalate(after),
+            Command::Tidy { older_than } => cli::tidy(older_than),
+            Command::Backup { keep } => cli::backup(keep),
+            Command::Import { format, list, file } => {
+                let added = import::import(format, &file, &list)?;
+                println!("Imported {} todos into {}", added, list);
+                Ok(())
+            }
+            Command::Export { format, list } => cli::export(format, list),
+            Command::Add { text, list, no_suggest, from_stdin, open } => {
+                let added_to = cli::add(text, list.clone(), from_stdin, !no_suggest)?;
+                // Keys are read from the terminal even when stdin was a pipe.
+                match open {
+                    true => start_tui(None, workspace, added_to.or(list)),
+                    false => Ok(()),
+                }
+            }
+            Command::Publish { list, bind, port } => cli::publish(list, bind, port),
+            Command::Changes { since } => cli::changes(since),
+            Command::Listen { socket } => cli::listen(socket.unwrap_or_else(|| todo_dir().join("todo.sock"))),
+            Command::List { list, all, tags, fields, search, due_from, due_to, limit, sort, template, template_file, header, footer } => {
+                let template = match template_file {
+                    Some(path) => std::fs::read_to_string(path)?,
+                    None => template,
+                };
+                let mut query = TodoQuery::new().order(sort);
+                if !all {
+                    query = query.completed(false);
+                }
+                for tag in &tags {
+                    query = query.tag(tag);
+                }
+                for (key, value) in &fields {
+                    query = query.field(key, value);
+                }
+                if let Some(text) = &search {
+                    query = query.text(text);
+                }
+                if let Some(date) = due_from {
+                    query = query.due_from(date);
+                }
+                if let Some(date) = due_to {
+                    query = query.due_to(date);
+                }
+                if let Some(limit) = limit {
+                    query = query.limit(limit);
+                }
+                cli::list(cli::ListOptions { list, query, template, header, footer })
+            }
+            Command::Diff { old, new, since } => match (old, new, since) {
+                (Some(old), Some(new), _) => diff::diff_files(&old, &new),
+                (_, _, Some(since)) => diff::diff_since(since),
+                _ => unreachable!("clap requires two files or --since"),
+            },
+            Command::Plugin { command } => match command {
+                PluginCommand::List => plugin::list(),
+                PluginCommand::Run { name, args } => plugin::run(&name, &args, &workspace),
+            },
+            Command::Template { command } => match command {
+                TemplateCommand::Install { source, list } => cli::install_template(&source, list),
+            },
+            Command::Report { command } => match command {
+                ReportCommand::Aging { oldest, json } => cli::aging(oldest, json),
+            },
+            Command::Daemon { command } => match command {
+                DaemonCommand::Install => daemon::install(),
+                DaemonCommand::Run => jobs::run_daemon(),
+                DaemonCommand::Status => jobs::print_status(),
+            },
+            Command::Tutorial => {
+                let _sandbox = database::use_in_memory_database("todo-tutorial")?;
+                start_tui(Some(0), workspace, None)
+            }
+        };
+    }
     let date = args.date;
     let count = args.count;
+    if let Some(date) = date.filter(|_| !count && (args.long || args.group_by.is_some())) {
+        return cli::due(date, args.long, args.group_by);
+    }
     if date.is_some() || count {
-        let todos = fetch_incomplete_todos(date.unwrap_or(Local::now().naive_local().date()));
-        match todos {
-            Ok(todos) =>
-                match count {
-                    true => println!("{}", todos.len()),
-                    false => { 
-                        todos.iter().for_each(|t| println!("{}\t{}\t{:?}", t.id.unwrap_or(0), t.due_date.expect("Has to have a date to be fetched"), t.title,));
-                    }
-                },
-            Err(e) => println!("Err: {:?}", e)
+        let date = date.unwrap_or(Local::now().naive_local().date());
+        let todos = match fetch_todos(&TodoQuery::new().completed(false).due_to(date)) {
+            Ok(todos) => todos,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
         };
+        match count {
+            true => println!("{}", todos.len()),
+            false => {
+                for todo in &todos {
+                    let due = todo.due_date.expect("Has to have a date to be fetched");
+                    println!("{}\t{}\t{}", todo.id.unwrap_or(0), due, todo.title);
+                }
+            }
+        }
         return Ok(()); 
     } 
 
-    let state = State {
+    start_tui(None, workspace, None)
+}
+
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("Expected KEY=VALUE, got {}", value)),
+    }
+}
+
+/// Runs the TUI, with `focus` inside the list of that title.
+fn start_tui(tutorial: Option<usize>, workspace: String, focus: Option<String>) -> Result<(), Box<dyn Error>> {
+    let mut state = State {
         state: AppState::List(None),
         list_title: "".to_string(),
+        list_due_date: None,
         input: "".to_string(),
         todo_title: "".to_string(),
         todo_description: "".to_string(),
         todo_due_date: None,
-        lists_list_state: ListState::default(),
-        todo_list_state: ListState::default(),
+        todo_slot: None,
+        lists_cursor: Cursor::default(),
+        todos_cursor: Cursor::default(),
         selecting_list: true,
+        macros: Macros::default(),
+        config: Config::load(),
+        jump_history: vec![],
+        today: Local::now().date_naive(),
+        message: None,
+        tutorial,
+        filter: None,
+        workspace,
+        workspace_views: HashMap::new(),
+        locked: None,
+        quota_warning: None,
+        pending_delete: None,
+        queue: Queue::default(),
+        plugins: Plugins::default(),
     };
+    if let Some(index) = focus.and_then(|title| get_lists().iter().position(|l| l.title == title)) {
+        state.lists_cursor.select(Some(index));
+        state.todos_cursor.select(Some(0));
+        state.selecting_list = false;
+    }
+    // The tutorial's database is thrown away, nothing to protect.
+    if state.config.pin.is_some() && state.tutorial.is_none() {
+        state.locked = Some(String::new());
+    }
     let mut terminal = setup_terminal()?;
-    run(&mut terminal, state)?;
+    render::set_reduced(state.config.reduced_rendering());
+    render::set_plain(state.config.no_color(), state.config.plain);
+    render::set_reduced_motion(state.config.reduced_motion);
+    if let Err(error) = database::check_db() {
+        match recover(&mut terminal, error)? {
+            Some(message) => state.message = Some(message),
+            None => return restore_terminal(&mut terminal),
+        }
+    }
+    // Keys the tutorial doesn't explain would only get in its way.
+    if state.tutorial.is_none() {
+        state.plugins = Plugins::load();
+        if state.message.is_none() {
+            state.message = state.plugins.errors.first().cloned();
+        }
+    }
+    let result = run(&mut terminal, state);
     restore_terminal(&mut terminal)?;
-    Ok(())
+    result
 }
 
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>, Box<dyn Error>> {
@@ -116,25 +747,53 @@ fn restore_terminal(
     Ok(terminal.show_cursor()?)
 }
 
+fn read_key() -> io::Result<Option<KeyEvent>> {
+    if event::poll(Duration::from_millis(250))? {
+        if let Event::Key(key) = event::read()? {
+            return Ok(Some(key));
+        }
+    }
+    Ok(None)
+}
+
 fn get_todos(list_id: usize) -> Vec<Todo> {
-    let todos = fetch_todos(list_id);
-    return match todos {
+    let todos = fetch_todos(&TodoQuery::new().list(list_id).order(Order::Due));
+    match todos {
         Ok(mut todos) => {
-            todos.sort_by_key(|t| t.due_date);
-            todos.sort_by_key(|t| !t.due_date.is_some());
             todos.sort_by_key(|t| t.completed);
-            return todos;
+            todos
         },
         Err(_) => vec![],
-    };
+    }
+}
+
+/// The todos of a list as the todos pane shows them, grouped under the
+/// list's milestones and narrowed down by `filter` when it is for this list.
+/// A todo pending deletion is left out.
+fn get_grouped_todos(
+    list_id: usize,
+    filter: Option<&QuickFilter>,
+    pending: Option<&PendingDelete>,
+) -> (Vec<Todo>, Vec<Header>) {
+    let mut todos = get_todos(list_id);
+    if let Some(Deleted::Todo(id)) = pending.map(|p| &p.deleted) {
+        todos.retain(|todo| todo.id != Some(*id));
+    }
+    let filter = filter.filter(|f| f.list_id == list_id);
+    if let Some(filter) = filter {
+        todos.retain(|todo| filter.matches(todo));
+    }
+    let (todos, mut headers) = milestones::group(todos, fetch_milestones(list_id).unwrap_or_default());
+    // Without the filter empty milestones can't exist, with it they are noise.
+    if filter.is_some() {
+        headers.retain(|h| h.total > 0);
+    }
+    (todos, headers)
 }
 
 fn get_lists() -> Vec<TodoList> {
     let lists = fetch_lists();
-    return match lists {
-        Ok(it) => it,
-        Err(_) => return vec![],
-    };
+    lists.unwrap_or_default()
 }
 
 fn run(
@@ -143,597 +802,1977 @@ fn run(
 ) -> Result<(), Box<dyn Error>> {
     let mut lists = get_lists();
     let mut todos = vec![];
+    let mut headers = vec![];
+    let mut timeline_todos = None;
+    if lists.is_empty() && state.tutorial.is_none() {
+        state.state = AppState::Onboarding(OnboardingStep::CreateList);
+    }
+    let mut redraw = true;
+    let mut last_size = terminal.size()?;
+    let mut escalated = None;
+    let mut last_input = Instant::now();
 
-    Ok(loop {
-        match state.state {
-            AppState::List(detail) => {
-                lists = get_lists();
-                todos = match state.lists_list_state.selected() {
-                    Some(list_index) => get_todos(lists[list_index].id.expect("Id exists")),
-                    None => vec![],
-                };
-                match detail {
-                    Some(v) => draw_lists_with_details(terminal, &lists, &todos, &mut state, v),
-                    None => draw_lists(terminal, &lists, &todos, &mut state),
-                }
+    loop {
+        // The app may be left open across midnight. Every view derives its
+        // groupings and highlights from state.today, and the key poll times
+        // out regularly, so the next draw after the rollover shows the new day.
+        let today = Local::now().date_naive();
+        let size = terminal.size()?;
+        if today != state.today || size != last_size {
+            redraw = true;
+        }
+        state.today = today;
+        last_size = size;
+
+        // Once at startup and again whenever the day changes.
+        if escalated != Some(today) {
+            if let Some(days) = state.config.escalate_after {
+                state.queue.submit(Operation::Escalate(days, today));
             }
-            AppState::Create(field, _) => draw_create_todo(terminal, &state, field),
+            escalated = Some(today);
+        }
 
-            AppState::CreateList(field) => draw_create_list(terminal, &state, field),
-        };
+        // The undo toast only shows in the list view, leaving it ends the
+        // chance to undo.
+        let undo_over = state.pending_delete.as_ref().is_some_and(|pending| {
+            pending.since.elapsed() >= Duration::from_secs(UNDO_SECONDS) || !matches!(state.state, AppState::List(_))
+        });
+        if undo_over {
+            finish_delete(&mut state);
+            redraw = true;
+        }
+
+        if let Some(message) = state.queue.retry() {
+            state.message = Some(message);
+            redraw = true;
+        }
+
+        let idle = state.config.lock_after.map(|minutes| Duration::from_secs(minutes * 60));
+        if state.config.pin.is_some() && state.locked.is_none() && idle.is_some_and(|idle| last_input.elapsed() >= idle) {
+            state.locked = Some(String::new());
+            redraw = true;
+        }
+
+        // Nothing but the PIN prompt is drawn or handled while locked, not
+        // even replayed macros.
+        if let Some(entered) = &state.locked {
+            if redraw || !render::on_change_only() {
+                redraw = false;
+                draw_lock(terminal, entered.chars().count(), state.message.as_deref());
+            }
+            if let Some(key) = read_key()? {
+                last_input = Instant::now();
+                redraw = true;
+                unlock(&mut state, key);
+            }
+            continue;
+        }
 
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                match state.state {
-                    AppState::List(detail) => match key.code {
-                        KeyCode::Char('q') => {
-                            break;
+        // Reduced rendering and motion skip redrawing until something changed.
+        if redraw || !render::on_change_only() {
+            redraw = false;
+            if state.config.list_quota.is_some() || state.config.todo_quota.is_some() {
+                state.quota_warning = count_todos()
+                    .ok()
+                    .and_then(|(total, open)| quota_warning(&state.config, total, &open));
+            }
+            if !matches!(state.state, AppState::Timeline) {
+                timeline_todos = None;
+            }
+            match state.state {
+                AppState::List(detail) => {
+                    lists = get_lists();
+                    if let Some(Deleted::List(id)) = state.pending_delete.as_ref().map(|p| &p.deleted) {
+                        lists.retain(|list| list.id != Some(*id));
+                    }
+                    state.lists_cursor.restore(lists.iter().map(|l| l.id.expect("Id exists")).collect());
+                    (todos, headers) = match state.lists_cursor.get(&lists) {
+                        Some(list) => get_grouped_todos(list.id.expect("Id exists"), state.filter.as_ref(), state.pending_delete.as_ref()),
+                        None => (vec![], vec![]),
+                    };
+                    // The detail view holds an index as well, follow it by id too.
+                    let detail_id = detail.and_then(|v| state.todos_cursor.id_at(v));
+                    state.todos_cursor.restore(todos.iter().map(|t| t.id.expect("Id exists")).collect());
+                    let detail = detail_id
+                        .and_then(|id| todos.iter().position(|t| t.id == Some(id)))
+                        .or(detail);
+                    state.state = AppState::List(detail);
+                    match detail {
+                        Some(v) => {
+                            let details = todos.get(v).map(Details::fetch).unwrap_or_default();
+                            draw_lists_with_details(terminal, &lists, &todos, &headers, &mut state, v, &details)
                         }
-                        KeyCode::Char('v') => {
-                            match detail {
-                                Some(_) => state.state = AppState::List(None),
-                                None => {
-                                    match state.todo_list_state.selected() {
-                                        Some(index) => {state.state = AppState::List(Some(index))}
-                                        None => ()
-                                    }
+                        None => draw_lists(terminal, &lists, &todos, &headers, &mut state),
+                    }
+                }
+                AppState::Create(field, _) => draw_create_todo(terminal, &state, field),
+
+                AppState::CreateList(field, edit_list_index) => draw_create_list(terminal, &state, field, edit_list_index.is_some()),
+
+                AppState::Stats => {
+                    let today = state.today;
+                    let stats: Vec<ListStats> = lists
+                        .iter()
+                        .map(|list| list_stats(list, &get_todos(list.id.expect("Id exists")), today))
+                        .collect();
+                    draw_stats(terminal, &stats, &keymap::hint(&state))
+                }
+
+                AppState::Today => {
+                    let due = fetch_todos(&TodoQuery::new().completed(false).due_to(state.today)).unwrap_or_default();
+                    draw_today(terminal, &lists, &due, state.today, &keymap::hint(&state))
+                }
+
+                AppState::Timeline => {
+                    let list = state.lists_cursor.get(&lists);
+                    // Nothing can change the todos from the timeline, they
+                    // are loaded once on opening it.
+                    let todos = timeline_todos.get_or_insert_with(|| {
+                        list.map(|l| get_timeline_todos(l.id.expect("Id exists"))).unwrap_or_default()
+                    });
+                    let title = list.map(|l| l.title.as_str()).unwrap_or_default();
+                    draw_timeline(terminal, title, todos, state.today, &keymap::hint(&state))
+                }
+
+                AppState::Onboarding(step) => draw_onboarding(terminal, &state, step),
+
+                AppState::Link(_) => draw_prompt(terminal, "Link to todo id, optionally followed by a relation type", &state),
+
+                AppState::Workspaces(index) => draw_workspaces(terminal, &state, index),
+
+                AppState::Milestone(_) => draw_prompt(terminal, "Milestone, leave empty to remove the todo from its milestone", &state),
+
+                AppState::Tags(index) => draw_tags(terminal, &state, index, &fetch_tags().unwrap_or_default()),
+
+                AppState::TagEdit(index, edit) => {
+                    let tags = fetch_tags().unwrap_or_default();
+                    let tag = tags.get(index).map(|(name, _)| name.as_str()).unwrap_or_default();
+                    let title = match edit {
+                        TagEdit::Rename => format!("Rename #{} to", tag),
+                        TagEdit::Merge => format!("Merge #{} into", tag),
+                        TagEdit::Recolor => format!("Style for #{}, e.g. bold red or yellow on blue", tag),
+                    };
+                    draw_prompt(terminal, &title, &state)
+                }
+            };
+        }
+
+        // Esc stops a running replay, other keys typed meanwhile are dropped.
+        if state.macros.replaying() && event::poll(Duration::ZERO)? {
+            if let Event::Key(KeyEvent { code: KeyCode::Esc, .. }) = event::read()? {
+                state.macros.stop_replay();
+                state.message = Some("Replay stopped".to_string());
+                redraw = true;
+                continue;
+            }
+        }
+        let (key, replayed) = match state.macros.next_replayed() {
+            Some(key) => (Some(key), true),
+            None => (read_key()?, false),
+        };
+        if let Some(key) = key {
+            last_input = Instant::now();
+            redraw = true;
+            state.message = None;
+            if !replayed {
+                // Digits bound by plugins go to them rather than to a count.
+                let bound = match key.code {
+                    KeyCode::Char(c) => {
+                        state.plugins.keys().iter().any(|(k, _)| *k == c) || state.config.key_actions.contains_key(&c)
+                    }
+                    _ => false,
+                };
+                let digit = matches!(key.code, KeyCode::Char('0'..='9'));
+                if matches!(state.state, AppState::List(_)) && !(digit && bound) && state.macros.handle(key) {
+                    continue;
+                }
+                state.macros.record(key);
+            }
+            let action = keymap::mode(&state).and_then(|mode| keymap::action(mode, key.code));
+            match state.state {
+                AppState::List(detail) => match action {
+                    Some(Action::Quit) => {
+                        break;
+                    }
+                    Some(Action::ToggleDetails) => {
+                        match detail {
+                            Some(_) => state.state = AppState::List(None),
+                            None => {
+                                if let Some(index) = state.todos_cursor.selected() {
+                                    state.state = AppState::List(Some(index))
                                 }
                             }
                         }
-                        KeyCode::Char('E') => {
-                            if state.lists_list_state.selected().is_some() {
-                                if let Some(edit_todo_index) = state.todo_list_state.selected() {
-                                    let todo = &todos[edit_todo_index];
-                                    state.todo_description = todo.description.clone().unwrap_or("".to_string());
-                                    state.input = todo.title.clone();
-                                    state.todo_title = todo.title.clone();
-                                    state.todo_due_date = todo.due_date.clone();
-                                    state.state = AppState::Create(Some(InputField::Title), Some(edit_todo_index));
-                                }
+                    }
+                    Some(Action::FollowLink) => {
+                        let link = detail
+                            .and_then(|index| todos.get(index))
+                            .and_then(|todo| get_links(todo).into_iter().next());
+                        if let Some((_, target)) = link {
+                            if let Some(current) = detail.and_then(|index| todos.get(index)).and_then(|t| t.id) {
+                                state.jump_history.push(current);
                             }
+                            jump_to(&mut state, &lists, &target);
                         }
-                        KeyCode::Char('N') => {
-                            if state.lists_list_state.selected().is_some() {
-                                state.state = AppState::Create(Some(InputField::Title), None)
-                            }
+                    }
+                    Some(Action::JumpBack) => {
+                        if let Some(Ok(Some(target))) = state.jump_history.pop().map(fetch_todo) {
+                            jump_to(&mut state, &lists, &target);
                         }
-                        KeyCode::Char('L') => {
-                            state.state = AppState::CreateList(Some(InputField::Title))
-                        }
-                        KeyCode::Char('D') => match state.selecting_list {
-                            true => match state.lists_list_state.selected() {
-                                Some(list_index) => {
-                                    delete_list(
-                                        lists[list_index]
-                                            .id
-                                            .expect("Should get an id from the database create")
-                                            .clone(),
-                                    )
-                                    .ok();
-                                    state.lists_list_state.select(None);
-                                    state.todo_list_state.select(None);
-                                }
-                                None => {}
-                            },
-                            false => match state.todo_list_state.selected() {
-                                Some(todo_index) => {
-                                    delete_todo(
-                                        todos[todo_index]
-                                            .id
-                                            .expect("Should get an id from the database create"),
-                                    )
-                                    .ok();
+                    }
+                    Some(Action::Link) => {
+                        if let Some(todo_index) = state.todos_cursor.selected() {
+                            state.input = "".to_string();
+                            state.state = AppState::Link(todo_index);
+                        }
+                    }
+                    Some(Action::Edit) if state.selecting_list => {
+                        if let (Some(edit_list_index), Some(list)) = (state.lists_cursor.selected(), state.lists_cursor.get(&lists)) {
+                            state.list_title = list.title.clone();
+                            state.list_due_date = list.due_date;
+                            state.input = list.title.clone();
+                            state.state = AppState::CreateList(Some(InputField::Title), Some(edit_list_index));
+                        }
+                    }
+                    Some(Action::Edit) if state.lists_cursor.selected().is_some() => {
+                        if let (Some(edit_todo_index), Some(todo)) = (state.todos_cursor.selected(), state.todos_cursor.get(&todos)) {
+                            state.todo_description = todo.description.clone().unwrap_or("".to_string());
+                            state.input = todo.title.clone();
+                            state.todo_title = todo.title.clone();
+                            state.todo_due_date = todo.due_date;
+                            state.todo_slot = todo.slot;
+                            state.state = AppState::Create(Some(InputField::Title), Some(edit_todo_index));
+                        }
+                    }
+                    Some(Action::SetMilestone) => {
+                        if let (Some(todo_index), Some(todo)) = (state.todos_cursor.selected(), state.todos_cursor.get(&todos)) {
+                            state.input = milestones::position_of(&headers, todo)
+                                .map(|position| headers[position].milestone.title.clone())
+                                .unwrap_or_default();
+                            state.state = AppState::Milestone(todo_index);
+                        }
+                    }
+                    Some(Action::FilterRelated) => {
+                        state.filter = match state.filter {
+                            Some(_) => None,
+                            None => {
+                                let filter = state.todos_cursor.get(&todos).and_then(|todo| QuickFilter::from_todo(todo, &headers));
+                                if filter.is_none() {
+                                    state.message = Some("No tags or milestone to filter by".to_string());
                                 }
-                                None => {}
-                            },
-                        },
-                        KeyCode::Char('j') => match state.selecting_list {
-                            true => {
-                                lists_move_down(&mut state, &lists);
-                            }
-                            false => {
-                                todos_move_down(&mut state, &todos);
-                            }
-                        },
-                        KeyCode::Char('k') => match state.selecting_list {
-                            true => {
-                                lists_move_up(&mut state);
+                                filter
                             }
-                            false => {
-                                todos_move_up(&mut state);
+                        };
+                    }
+                    Some(Action::Fold) => {
+                        let position = state.todos_cursor.get(&todos).and_then(|todo| milestones::position_of(&headers, todo));
+                        if let Some(milestone_id) = position.and_then(|position| headers[position].milestone.id) {
+                            state.queue.submit(Operation::CollapseMilestone(milestone_id, true));
+                        }
+                    }
+                    Some(Action::UnfoldAll) => {
+                        if let Some(list) = state.lists_cursor.get(&lists) {
+                            state.queue.submit(Operation::ExpandMilestones(list.id.expect("Id exists")));
+                        }
+                    }
+                    Some(action @ (Action::MilestoneEarlier | Action::MilestoneLater)) => {
+                        // Moves the milestone of the selected todo one place
+                        // earlier or later.
+                        let position = state.todos_cursor.get(&todos).and_then(|todo| milestones::position_of(&headers, todo));
+                        if let Some(position) = position {
+                            let other = match action {
+                                Action::MilestoneEarlier => position.checked_sub(1),
+                                _ => Some(position + 1),
+                            };
+                            if let Some(other) = other.filter(|&other| other < headers.len()) {
+                                let (a, b) = (headers[position].milestone.clone(), headers[other].milestone.clone());
+                                state.queue.submit(Operation::SwapMilestones(a, b));
                             }
+                        }
+                    }
+                    Some(Action::CyclePriority) => {
+                        cycle_priority(&mut state, &todos);
+                    }
+                    Some(Action::NewTodo) if state.lists_cursor.selected().is_some() => {
+                        state.todo_description = state
+                            .lists_cursor
+                            .get(&lists)
+                            .and_then(|list| state.config.description_template(&list.title))
+                            .unwrap_or_default()
+                            .to_string();
+                        state.state = AppState::Create(Some(InputField::Title), None)
+                    }
+                    Some(Action::NewList) => {
+                        state.state = AppState::CreateList(Some(InputField::Title), None)
+                    }
+                    Some(Action::Stats) => {
+                        state.state = AppState::Stats
+                    }
+                    Some(Action::Today) => {
+                        state.state = AppState::Today
+                    }
+                    // The tutorial runs in a database of its own.
+                    Some(Action::Workspaces) if state.tutorial.is_none() => {
+                        let current = state.config.workspaces().iter().position(|(name, _)| *name == state.workspace);
+                        state.state = AppState::Workspaces(current.unwrap_or(0));
+                    }
+                    Some(Action::Timeline) if state.lists_cursor.selected().is_some() => {
+                        state.state = AppState::Timeline
+                    }
+                    Some(Action::Tags) => {
+                        state.state = AppState::Tags(0)
+                    }
+                    Some(Action::Delete) => match state.selecting_list {
+                        true => if let Some(list) = state.lists_cursor.get(&lists) {
+                            let deleted = Deleted::List(list.id.expect("Should get an id from the database create"));
+                            start_delete(&mut state, deleted, list.title.clone());
+                            state.lists_cursor.select(None);
+                            state.todos_cursor.select(None);
                         },
-                        KeyCode::Char('h') => match state.selecting_list {
-                            true => {}
-                            false => {
-                                state.selecting_list = true;
+                        false => if let Some(todo) = state.todos_cursor.get(&todos) {
+                            let deleted = Deleted::Todo(todo.id.expect("Should get an id from the database create"));
+                            start_delete(&mut state, deleted, todo.title.clone());
+                            // The refetch on the next draw keeps the cursor
+                            // on the same position, clamped if this was the
+                            // last row.
+                            if let AppState::List(Some(_)) = state.state {
                                 state.state = AppState::List(None);
-                                state.todo_list_state.select(None);
-                            }
-                        },
-                        KeyCode::Char('l') => match state.selecting_list {
-                            true => {
-                                state.selecting_list = false;
-                                todos = match state.lists_list_state.selected() {
-                                    Some(index) => get_todos(lists[index].id.expect("Id exists")),
-                                    None => vec![],
-                                };
-                                if todos.len() > 0 {
-                                    state.todo_list_state.select(Some(0));
-                                }
-                            }
-                            false => {
-                                toggle_todo(&mut state, &todos);
-                            }
-                        },
-                        KeyCode::Char(' ') => match state.selecting_list {
-                            true => {}
-                            false => {
-                                toggle_todo(&mut state, &todos);
                             }
                         },
-                        _ => {}
                     },
-                    AppState::Create(field, edit_todo_index) => match field {
-                        Some(f) => match key.code {
-                            KeyCode::Char(c) => {
-                                state.input = format!("{}{}", state.input, c);
-                            }
-                            KeyCode::Backspace => {
-                                state.input.pop();
+                    Some(Action::UndoDelete) if state.pending_delete.is_some() => {
+                        state.pending_delete = None;
+                    }
+                    Some(Action::Down) => match state.selecting_list {
+                        true => {
+                            state.lists_cursor.down(lists.len());
+                        }
+                        false => {
+                            state.todos_cursor.down(todos.len());
+                        }
+                    },
+                    Some(Action::Up) => match state.selecting_list {
+                        true => {
+                            state.lists_cursor.up(lists.len());
+                        }
+                        false => {
+                            state.todos_cursor.up(todos.len());
+                        }
+                    },
+                    Some(Action::ToLists) => {
+                        state.selecting_list = true;
+                        state.state = AppState::List(None);
+                        state.todos_cursor.select(None);
+                    }
+                    Some(Action::OpenList) => {
+                        state.selecting_list = false;
+                        todos = match state.lists_cursor.get(&lists) {
+                            Some(list) => get_grouped_todos(list.id.expect("Id exists"), state.filter.as_ref(), state.pending_delete.as_ref()).0,
+                            None => vec![],
+                        };
+                        state.todos_cursor.first(todos.len());
+                    }
+                    Some(Action::Complete) => {
+                        toggle_todo(&mut state, &todos);
+                    }
+                    // Keys without a built-in binding are free for plugins,
+                    // scripts first.
+                    None => {
+                        let key = match key.code {
+                            KeyCode::Char(c) => Some(c),
+                            _ => None,
+                        };
+                        let ran = key.and_then(|c| state.plugins.run_key(c, state.todos_cursor.get(&todos)));
+                        let plugin = key.and_then(|c| state.config.key_actions.get(&c).cloned());
+                        if let Some(result) = ran {
+                            state.message = Some(result.unwrap_or_else(|e| e.to_string()));
+                        } else if let Some(name) = plugin {
+                            let mut env = vec![];
+                            if let Some(list) = state.lists_cursor.get(&lists) {
+                                env.push(("TODO_LIST_ID", list.id.unwrap_or(0).to_string()));
                             }
-                            KeyCode::Esc => {
-                                state.input = "".to_string();
-                                state.state = AppState::Create(None, edit_todo_index)
+                            if let Some(todo) = state.todos_cursor.get(&todos) {
+                                env.push(("TODO_ID", todo.id.unwrap_or(0).to_string()));
                             }
-                            KeyCode::Enter => match f {
-                                InputField::Title => {
-                                    state.todo_title = state.input.clone();
-                                    state.input = "".to_string();
-                                    state.state = AppState::Create(Some(InputField::Description), edit_todo_index);
+                            state.message = Some(match plugin::run_captured(&name, &state.workspace, &env) {
+                                Ok(output) if output.status.success() => {
+                                    let stdout = String::from_utf8_lossy(&output.stdout);
+                                    format!("{}: {}", name, stdout.lines().next().unwrap_or("done"))
                                 }
-                                InputField::Description => {
-                                    state.todo_description = state.input.clone();
+                                Ok(output) => format!("{} failed: {}", name, String::from_utf8_lossy(&output.stderr).trim()),
+                                Err(e) => e.to_string(),
+                            });
+                        }
+                    }
+                    _ => {}
+                },
+                AppState::Create(field, edit_todo_index) => match field {
+                    Some(f) => match (action, key.code) {
+                        (None, KeyCode::Char(c)) => {
+                            state.input = format!("{}{}", state.input, c);
+                        }
+                        (Some(Action::DeleteChar), _) => {
+                            state.input.pop();
+                        }
+                        (Some(Action::StopEditing), _) => {
+                            state.input = "".to_string();
+                            state.state = AppState::Create(None, edit_todo_index)
+                        }
+                        (Some(Action::NextField), _) => match f {
+                            InputField::Title => {
+                                state.todo_title = state.input.clone();
+                                state.input = state.todo_description.clone();
+                                state.state = AppState::Create(Some(InputField::Description), edit_todo_index);
+                            }
+                            InputField::Description => {
+                                state.todo_description = state.input.clone();
+                                state.input = state.todo_due_date.map(|d| d.to_string()).unwrap_or_default();
+                                state.state = AppState::Create(Some(InputField::DueDate), edit_todo_index);
+                            }
+                            // An invalid date keeps the field open, clearing
+                            // it removes the due date.
+                            InputField::DueDate => match due_feedback(&state) {
+                                Some(Err(_)) => {}
+                                due => {
+                                    state.todo_due_date = due.and_then(Result::ok);
                                     state.input = "".to_string();
-                                    state.state = AppState::Create(Some(InputField::DueDate), edit_todo_index);
+                                    state.state = AppState::Create(Some(InputField::Slot), edit_todo_index);
                                 }
-                                InputField::DueDate => {
-                                    let duedatestring = state.input.clone();
-                                    state.todo_due_date = match duedatestring.parse::<u64>() {
-                                        Ok(v) => Some(Local::now().checked_add_days(Days::new(v)).expect("in range").naive_local().date()),
-                                        Err(_) => None
-                                    };
+                            },
+                            // Like the due date, an unknown time of day
+                            // keeps the field open.
+                            InputField::Slot => match slot_feedback(&state) {
+                                Some(Err(_)) => {}
+                                slot => {
+                                    state.todo_slot = slot.and_then(Result::ok);
                                     state.input = "".to_string();
                                     state.state = AppState::Create(None, edit_todo_index);
                                 }
                             },
-                            _ => {}
                         },
-                        None => match key.code {
-                            KeyCode::Esc => {
-                                state.state = AppState::List(None);
-                            }
-                            KeyCode::Char('q') => {
-                                state.state = AppState::List(None);
-                            }
-                            KeyCode::Char('D') => {
-                                state.state = AppState::Create(Some(InputField::DueDate), edit_todo_index);
-                            }
-                            KeyCode::Char('d') => {
-                                state.state = AppState::Create(Some(InputField::Description), edit_todo_index);
-                                state.input = state.todo_description.clone();
-                            }
-                            KeyCode::Char('t') => {
-                                state.state = AppState::Create(Some(InputField::Title), edit_todo_index);
-                                state.input = state.todo_title.clone();
-                            }
-                            KeyCode::Char('s') => {
-                                match edit_todo_index {
-                                    Some(index) => {
-                                        let mut updated_todo = todos[index].clone();
+                        _ => {}
+                    },
+                    None => match action {
+                        Some(Action::Cancel) => {
+                            state.state = AppState::List(None);
+                        }
+                        Some(Action::EditDueDate) => {
+                            state.state = AppState::Create(Some(InputField::DueDate), edit_todo_index);
+                            state.input = state.todo_due_date.map(|d| d.to_string()).unwrap_or_default();
+                        }
+                        Some(Action::EditSlot) => {
+                            state.state = AppState::Create(Some(InputField::Slot), edit_todo_index);
+                            state.input = state.todo_slot.map(|s| s.to_string()).unwrap_or_default();
+                        }
+                        Some(Action::EditDescription) => {
+                            state.state = AppState::Create(Some(InputField::Description), edit_todo_index);
+                            state.input = state.todo_description.clone();
+                        }
+                        Some(Action::EditTitle) => {
+                            state.state = AppState::Create(Some(InputField::Title), edit_todo_index);
+                            state.input = state.todo_title.clone();
+                        }
+                        Some(Action::Save) => {
+                            match edit_todo_index {
+                                Some(index) => {
+                                    if let Some(todo) = todos.get(index) {
+                                        let mut updated_todo = todo.clone();
                                         updated_todo.due_date = state.todo_due_date;
+                                        updated_todo.slot = state.todo_slot;
                                         updated_todo.title = state.todo_title;
                                         updated_todo.description = Some(state.todo_description);
-                                        // Should handle error
-                                        _ = update_todo(&updated_todo);
+                                        state.queue.submit(Operation::UpdateTodo(updated_todo));
                                     }
-                                    None => {
-                                        save_todo(
-                                            &state,
-                                            lists[state
-                                                .lists_list_state
-                                                .selected()
-                                                .expect("Need list id to create todo")]
-                                            .id
-                                            .expect("Id exists"),
-                                        );
+                                }
+                                None => {
+                                    if let Some(list) = state.lists_cursor.get(&lists) {
+                                        save_todo(&mut state, list.id.expect("Id exists"));
                                     }
                                 }
-                                state.todo_title = "".to_string();
-                                state.todo_description = "".to_string();
-                                state.todo_due_date = None;
-                                state.state = AppState::List(None);
                             }
-                            _ => {}
-                        },
+                            state.todo_title = "".to_string();
+                            state.todo_description = "".to_string();
+                            state.todo_due_date = None;
+                            state.todo_slot = None;
+                            state.state = AppState::List(None);
+                        }
+                        _ => {}
                     },
-                    AppState::CreateList(field) => match field {
-                        Some(f) => match key.code {
-                            KeyCode::Char(c) => {
-                                state.input = format!("{}{}", state.input, c);
-                            }
-                            KeyCode::Backspace => {
-                                state.input.pop();
-                            }
-                            KeyCode::Esc => {
+                },
+                AppState::CreateList(field, edit_list_index) => match field {
+                    Some(f) => match (action, key.code) {
+                        (None, KeyCode::Char(c)) => {
+                            state.input = format!("{}{}", state.input, c);
+                        }
+                        (Some(Action::DeleteChar), _) => {
+                            state.input.pop();
+                        }
+                        (Some(Action::StopEditing), _) => {
+                            state.input = "".to_string();
+                            state.state = AppState::CreateList(None, edit_list_index)
+                        }
+                        (Some(Action::NextField), _) => match f {
+                            InputField::Title => {
+                                state.list_title = state.input.clone();
                                 state.input = "".to_string();
-                                state.state = AppState::CreateList(None)
+                                state.state = AppState::CreateList(None, edit_list_index);
                             }
-                            KeyCode::Enter => match f {
-                                InputField::Title => {
-                                    state.list_title = state.input.clone();
+                            InputField::DueDate => match due_feedback(&state) {
+                                Some(Err(_)) => {}
+                                due => {
+                                    state.list_due_date = due.and_then(Result::ok);
                                     state.input = "".to_string();
-                                    state.state = AppState::CreateList(None);
+                                    state.state = AppState::CreateList(None, edit_list_index);
                                 }
-                                _ => {}
                             },
                             _ => {}
                         },
-                        None => match key.code {
-                            KeyCode::Esc => {
-                                state.state = AppState::List(None);
-                            }
-                            KeyCode::Char('q') => {
-                                state.state = AppState::List(None);
+                        _ => {}
+                    },
+                    None => match action {
+                        Some(Action::Cancel) => {
+                            state.state = AppState::List(None);
+                        }
+                        Some(Action::EditTitle) => {
+                            state.state = AppState::CreateList(Some(InputField::Title), edit_list_index);
+                            state.input = state.list_title.clone();
+                        }
+                        Some(Action::EditDueDate) => {
+                            state.state = AppState::CreateList(Some(InputField::DueDate), edit_list_index);
+                            state.input = state.list_due_date.map(|d| d.to_string()).unwrap_or_default();
+                        }
+                        Some(Action::Save) => {
+                            match edit_list_index.and_then(|index| lists.get(index)) {
+                                Some(list) => {
+                                    let list = TodoList {
+                                        id: list.id,
+                                        title: state.list_title.clone(),
+                                        due_date: state.list_due_date,
+                                    };
+                                    state.queue.submit(Operation::UpdateList(list));
+                                }
+                                None => {
+                                    let (title, due_date) = (state.list_title.clone(), state.list_due_date);
+                                    save_todo_list(&mut state, title, due_date);
+                                }
                             }
-                            KeyCode::Char('t') => {
-                                state.state = AppState::CreateList(Some(InputField::Title));
+                            state.input = "".to_string();
+                            state.list_due_date = None;
+                            state.state = AppState::List(None);
+                        }
+                        _ => {}
+                    },
+                },
+                AppState::Stats => match action {
+                    Some(Action::Back) => {
+                        state.state = AppState::List(None);
+                    }
+                    _ => {}
+                },
+                AppState::Today => match action {
+                    Some(Action::Back) => {
+                        state.state = AppState::List(None);
+                    }
+                    _ => {}
+                },
+                AppState::Workspaces(index) => match action {
+                    Some(Action::Back) => {
+                        state.state = AppState::List(None);
+                    }
+                    Some(Action::Down) => {
+                        let last = state.config.workspaces().len().saturating_sub(1);
+                        state.state = AppState::Workspaces((index + 1).min(last));
+                    }
+                    Some(Action::Up) => {
+                        state.state = AppState::Workspaces(index.saturating_sub(1));
+                    }
+                    Some(Action::Switch) => {
+                        if let Some((name, path)) = state.config.workspaces().into_iter().nth(index) {
+                            if switch_workspace(&mut state, name, path) {
+                                // Run the maintenance pass on the new database too.
+                                escalated = None;
                             }
-                            KeyCode::Char('s') => {
-                                save_todo_list(state.list_title.clone());
-                                state.input = "".to_string();
-                                state.state = AppState::List(None);
+                        }
+                        state.state = AppState::List(None);
+                    }
+                    _ => {}
+                },
+                AppState::Tags(index) => {
+                    let tags = fetch_tags().unwrap_or_default();
+                    let tag = tags.get(index).map(|(name, _)| name.clone());
+                    match action {
+                        Some(Action::Back) => {
+                            state.state = AppState::List(None);
+                        }
+                        Some(Action::Down) => {
+                            state.state = AppState::Tags((index + 1).min(tags.len().saturating_sub(1)));
+                        }
+                        Some(Action::Up) => {
+                            state.state = AppState::Tags(index.saturating_sub(1));
+                        }
+                        Some(Action::Rename) if tag.is_some() => {
+                            state.input = tag.unwrap_or_default();
+                            state.state = AppState::TagEdit(index, TagEdit::Rename);
+                        }
+                        Some(Action::Merge) if tag.is_some() => {
+                            state.input = "".to_string();
+                            state.state = AppState::TagEdit(index, TagEdit::Merge);
+                        }
+                        Some(Action::Recolor) if tag.is_some() => {
+                            state.input = "".to_string();
+                            state.state = AppState::TagEdit(index, TagEdit::Recolor);
+                        }
+                        Some(Action::RemoveTag) => {
+                            if let Some(tag) = tag {
+                                state.message = Some(match retag(&tag, None) {
+                                    Ok(changed) => format!("Removed #{} from {} todos", tag, changed),
+                                    Err(e) => e.to_string(),
+                                });
+                                state.state = AppState::Tags(index.min(tags.len().saturating_sub(2)));
                             }
-                            _ => {}
-                        },
-                    },
+                        }
+                        _ => {}
+                    }
                 }
+                AppState::TagEdit(index, edit) => match (action, key.code) {
+                    (None, KeyCode::Char(c)) => {
+                        state.input = format!("{}{}", state.input, c);
+                    }
+                    (Some(Action::DeleteChar), _) => {
+                        state.input.pop();
+                    }
+                    (Some(Action::Cancel), _) => {
+                        state.input = "".to_string();
+                        state.state = AppState::Tags(index);
+                    }
+                    (Some(Action::Save), _) => {
+                        let tags = fetch_tags().unwrap_or_default();
+                        if let Some((tag, _)) = tags.get(index) {
+                            let (message, selected) = edit_tag(&mut state, &tags, tag, edit);
+                            // Follow the tag to where its new name sorts.
+                            let index = fetch_tags()
+                                .unwrap_or_default()
+                                .iter()
+                                .position(|(name, _)| *name == selected)
+                                .unwrap_or(index);
+                            state.message = Some(message);
+                            state.state = AppState::Tags(index);
+                        }
+                        state.input = "".to_string();
+                    }
+                    _ => {}
+                },
+                AppState::Timeline => match action {
+                    Some(Action::Back) => {
+                        state.state = AppState::List(None);
+                    }
+                    _ => {}
+                },
+                AppState::Onboarding(step) => match (step, key.code) {
+                    (_, KeyCode::Esc) => {
+                        state.input = "".to_string();
+                        state.state = AppState::List(None);
+                    }
+                    (OnboardingStep::CreateList | OnboardingStep::Import, KeyCode::Char(c)) => {
+                        state.input = format!("{}{}", state.input, c);
+                    }
+                    (OnboardingStep::CreateList | OnboardingStep::Import, KeyCode::Backspace) => {
+                        state.input.pop();
+                    }
+                    (OnboardingStep::CreateList, KeyCode::Enter) if !state.input.trim().is_empty() => {
+                        let title = state.input.trim().to_string();
+                        save_todo_list(&mut state, title, None);
+                        state.input = "".to_string();
+                        state.state = AppState::Onboarding(OnboardingStep::Import);
+                    }
+                    (OnboardingStep::Import, KeyCode::Enter) => {
+                        let path = state.input.trim().to_string();
+                        if !path.is_empty() {
+                            let format = ImportFormat::NetscapeBookmarks;
+                            state.message = Some(match import::import(format, path.as_ref(), "Reading") {
+                                Ok(added) => format!("Imported {} todos into Reading", added),
+                                Err(e) => format!("Could not import {}: {}", path, e),
+                            });
+                        }
+                        state.input = "".to_string();
+                        state.state = AppState::Onboarding(OnboardingStep::Theme);
+                    }
+                    (OnboardingStep::Theme, KeyCode::Char(c @ '1'..='3')) => {
+                        let (_, rules) = THEMES[c as usize - '1' as usize];
+                        Config::append(rules).ok();
+                        state.config = Config::load();
+                        state.state = AppState::Onboarding(OnboardingStep::Keys);
+                    }
+                    (OnboardingStep::Theme, KeyCode::Enter) => {
+                        state.state = AppState::Onboarding(OnboardingStep::Keys);
+                    }
+                    (OnboardingStep::Keys, KeyCode::Enter) => {
+                        lists = get_lists();
+                        if !lists.is_empty() {
+                            state.lists_cursor.select(Some(0));
+                        }
+                        state.state = AppState::List(None);
+                    }
+                    _ => {}
+                },
+                AppState::Milestone(todo_index) => match (action, key.code) {
+                    (None, KeyCode::Char(c)) => {
+                        state.input = format!("{}{}", state.input, c);
+                    }
+                    (Some(Action::DeleteChar), _) => {
+                        state.input.pop();
+                    }
+                    (Some(Action::Cancel), _) => {
+                        state.input = "".to_string();
+                        state.state = AppState::List(None);
+                    }
+                    (Some(Action::Save), _) => {
+                        if let Some(todo) = todos.get(todo_index) {
+                            let title = state.input.trim();
+                            let existing = headers.iter().find(|h| h.milestone.title.eq_ignore_ascii_case(title));
+                            let operation = match existing {
+                                // A new milestone is added with the todo, so the
+                                // todo isn't left without one if that fails.
+                                None if !title.is_empty() => Operation::SetMilestone(todo.clone(), title.to_string()),
+                                _ => Operation::UpdateTodo(Todo {
+                                    milestone_id: existing.and_then(|header| header.milestone.id),
+                                    ..todo.clone()
+                                }),
+                            };
+                            state.queue.submit(operation);
+                        }
+                        state.input = "".to_string();
+                        state.state = AppState::List(None);
+                    }
+                    _ => {}
+                },
+                AppState::Link(todo_index) => match (action, key.code) {
+                    (None, KeyCode::Char(c)) => {
+                        state.input = format!("{}{}", state.input, c);
+                    }
+                    (Some(Action::DeleteChar), _) => {
+                        state.input.pop();
+                    }
+                    (Some(Action::Cancel), _) => {
+                        state.input = "".to_string();
+                        state.state = AppState::List(None);
+                    }
+                    (Some(Action::Save), _) => {
+                        let mut parts = state.input.split_whitespace();
+                        let related_id = parts.next().and_then(|id| id.parse::<usize>().ok());
+                        let kind = parts.collect::<Vec<_>>().join(" ");
+                        if let (Some(todo_id), Some(related_id)) = (todos.get(todo_index).and_then(|t| t.id), related_id) {
+                            if todo_id != related_id && matches!(fetch_todo(related_id), Ok(Some(_))) {
+                                let relation = Relation {
+                                    todo_id,
+                                    related_id,
+                                    kind: if kind.is_empty() { "related".to_string() } else { kind },
+                                };
+                                state.queue.submit(Operation::AddRelation(relation));
+                            }
+                        }
+                        state.input = "".to_string();
+                        state.state = AppState::List(Some(todo_index));
+                    }
+                    _ => {}
+                },
             }
+            tutorial::advance(&mut state);
         }
-    })
+    }
+    finish_delete(&mut state);
+    // Whatever is still refused is lost, say so once the screen is back.
+    let unsaved = state.queue.flush();
+    match unsaved.is_empty() {
+        true => Ok(()),
+        false => Err(format!("Could not save: {}", unsaved.join(", ")).into()),
+    }
 }
 
-fn save_todo_list(title: String) {
-    let list = TodoList { title, id: None };
-    add_list(&list).ok();
+/// Hides what is deleted until the undo time is up, finishing an earlier
+/// delete right away.
+fn start_delete(state: &mut State, deleted: Deleted, title: String) {
+    finish_delete(state);
+    state.pending_delete = Some(PendingDelete {
+        deleted,
+        title,
+        since: Instant::now(),
+    });
+}
+
+fn finish_delete(state: &mut State) {
+    match state.pending_delete.take().map(|pending| pending.deleted) {
+        Some(Deleted::Todo(id)) => state.queue.submit(Operation::DeleteTodo(id)),
+        Some(Deleted::List(id)) => state.queue.submit(Operation::DeleteList(id)),
+        None => {}
+    }
+}
+
+/// What the detail view shows besides the todo itself.
+#[derive(Default)]
+struct Details {
+    links: Vec<(String, Todo)>,
+    attachments: Vec<String>,
+    fields: Vec<(String, String)>,
+}
+
+impl Details {
+    fn fetch(todo: &Todo) -> Details {
+        let id = todo.id.expect("Id exists");
+        Details {
+            links: get_links(todo),
+            attachments: fetch_attachments(id).unwrap_or_default(),
+            fields: fetch_fields(id).unwrap_or_default(),
+        }
+    }
+}
+
+/// The todos linked to `todo`, together with the relation type.
+fn get_links(todo: &Todo) -> Vec<(String, Todo)> {
+    let relations = todo.id.map(fetch_relations).and_then(Result::ok).unwrap_or_default();
+    relations
+        .into_iter()
+        .filter_map(|r| match fetch_todo(r.related_id) {
+            Ok(Some(related)) => Some((r.kind, related)),
+            _ => None,
+        })
+        .collect()
 }
 
-fn save_todo(state: &State, list_id: usize) {
+/// Switches to the database of another workspace, bringing back the
+/// selection it was left with. Changes still waiting are saved first, as
+/// they belong to the current database; if that fails the switch is refused
+/// and `false` returned.
+fn switch_workspace(state: &mut State, name: String, path: Option<PathBuf>) -> bool {
+    if name == state.workspace {
+        return false;
+    }
+    finish_delete(state);
+    let unsaved = state.queue.flush();
+    if !unsaved.is_empty() {
+        state.message = Some(format!("Not switching, could not save: {}", unsaved.join(", ")));
+        return false;
+    }
+    let view = state.workspace_views.remove(&name).unwrap_or_default();
+    let previous = WorkspaceView {
+        lists_cursor: mem::replace(&mut state.lists_cursor, view.lists_cursor),
+        todos_cursor: mem::replace(&mut state.todos_cursor, view.todos_cursor),
+        selecting_list: mem::replace(&mut state.selecting_list, view.selecting_list),
+        jump_history: mem::replace(&mut state.jump_history, view.jump_history),
+        filter: mem::replace(&mut state.filter, view.filter),
+    };
+    state.workspace_views.insert(mem::replace(&mut state.workspace, name), previous);
+    database::use_database(path);
+    true
+}
+
+/// Selects the list and todo of `target` and opens its details.
+fn jump_to(state: &mut State, lists: &[TodoList], target: &Todo) {
+    let Some(list_index) = lists.iter().position(|l| l.id == Some(target.list_id)) else {
+        return;
+    };
+    // A todo in a collapsed milestone isn't shown, unfold it first.
+    if let Some(milestone_id) = target.milestone_id {
+        state.queue.submit(Operation::CollapseMilestone(milestone_id, false));
+    }
+    // The target may not match the filter.
+    state.filter = None;
+    let (todos, _) = get_grouped_todos(target.list_id, None, None);
+    if let Some(todo_index) = todos.iter().position(|t| t.id == target.id) {
+        state.lists_cursor.select(Some(list_index));
+        state.todos_cursor.select(Some(todo_index));
+        state.selecting_list = false;
+        state.state = AppState::List(Some(todo_index));
+    }
+}
+
+fn save_todo_list(state: &mut State, title: String, due_date: Option<NaiveDate>) {
+    let list = TodoList { title, id: None, due_date };
+    state.queue.submit(Operation::AddList(list));
+}
+
+fn save_todo(state: &mut State, list_id: usize) {
     let todo = Todo {
-        id: None,
-        list_id,
-        title: state.todo_title.clone(),
         description: Some(state.todo_description.clone()),
-        due_date: state.todo_due_date.clone(),
-        completed: false,
-        completed_date: None,
-        dependencies: vec![],
+        due_date: state.todo_due_date,
+        slot: state.todo_slot,
+        created_date: Some(Local::now().date_naive()),
+        ..Todo::new(list_id, state.todo_title.clone())
     };
-    add_todo(&todo).ok();
+    state.queue.submit(Operation::AddTodo(todo));
 }
 
 fn toggle_todo(state: &mut State, todos: &[Todo]) {
-    match state.todo_list_state.selected() {
-        Some(todo_index) => {
-            toggle_todo_completion(
-                todos[todo_index]
-                    .id
-                    .expect("Should have an id from the database creation"),
-                !todos[todo_index].completed,
+    if let Some(todo) = state.todos_cursor.get(todos) {
+        let id = todo.id.expect("Should have an id from the database creation");
+        state.queue.submit(Operation::ToggleTodo(id, !todo.completed));
+    }
+}
+
+fn cycle_priority(state: &mut State, todos: &[Todo]) {
+    if let Some(todo) = state.todos_cursor.get(todos) {
+        let todo = Todo {
+            priority: todo.priority.cycled(),
+            ..todo.clone()
+        };
+        state.queue.submit(Operation::UpdateTodo(todo));
+    }
+}
+
+fn draw_create_list(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    state: &State,
+    input_field: Option<InputField>,
+    editing: bool,
+) {
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(2),
+                    Constraint::Min(5),
+                    Constraint::Length(4),
+                    Constraint::Length(4),
+                    Constraint::Length(2),
+                ]
+                .as_ref(),
             )
-            .ok();
+            .split(size);
+
+        frame.render_widget(
+            Paragraph::new(form_header(if editing { "Edit list" } else { "New list" }, state))
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            chunks[0],
+        );
+
+        let text = vec![
+            Line::from("(t) Input title"),
+            Line::from("(D) Input due date"),
+            Line::from("(s) Save list".green().italic()),
+            Line::from("(esc) Cancel".red()),
+        ];
+
+        frame.render_widget(
+            Paragraph::new(text.clone())
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            chunks[1],
+        );
+
+        frame.render_widget(
+            Paragraph::new(match input_field {
+                Some(InputField::Title) => state.input.clone(),
+                _ => state.list_title.clone(),
+            })
+            .block(
+                Block::default()
+                    .title("Title")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(match input_field {
+                Some(InputField::Title) => Color::Yellow,
+                _ => Color::White,
+            }))
+            .alignment(Alignment::Center),
+            chunks[2],
+        );
+
+        frame.render_widget(
+            Paragraph::new(due_text(state, input_field, state.list_due_date))
+            .block(
+                Block::default()
+                    .title("Due date (YYYY-MM-DD, +days, today or tomorrow)")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(match input_field {
+                Some(InputField::DueDate) => Color::Yellow,
+                _ => Color::White,
+            }))
+            .alignment(Alignment::Center),
+            chunks[3],
+        );
+        // The menu above lists the keys, except for those of the field.
+        if keymap::mode(state) == Some(keymap::Mode::Field) {
+            frame.render_widget(hint_bar(state), chunks[4]);
         }
-        None => {}
+    })
+    .ok();
+}
+
+/// What is going on above the panes: a macro being recorded and one line of
+/// feedback. The keys are in the hint bar below them, see `keymap::hint`.
+fn header_text(state: &State) -> String {
+    let header = match state.macros.recording() {
+        Some(register) => format!("[recording @{}]", register),
+        None => "".to_string(),
+    };
+    let undo = state
+        .pending_delete
+        .as_ref()
+        .map(|pending| format!("Deleted {} - press u to undo", pending.title));
+    match state
+        .message
+        .clone()
+        .or(undo)
+        .or_else(|| tutorial::hint(state))
+        .or_else(|| pending_text(state))
+        .or_else(|| state.quota_warning.clone())
+    {
+        Some(message) if header.is_empty() => message,
+        Some(message) => format!("{}\n{}", header, message),
+        None => header,
     }
 }
 
-fn todos_move_up(state: &mut State) {
-    match state.todo_list_state.selected() {
-        Some(v) => {
-            let max = match v {
-                0 => None,
-                v => Some(v - 1),
-            };
-            state.todo_list_state.select(max);
-        }
-        None => {
-            state.todo_list_state.select(Some(0));
-        }
+/// The keys of the current mode along the bottom of the screen.
+fn hint_bar<'a>(state: &State) -> Paragraph<'a> {
+    Paragraph::new(keymap::hint(state))
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+}
+
+fn pending_text(state: &State) -> Option<String> {
+    match state.queue.len() {
+        0 => None,
+        1 => Some("1 change not saved yet, retrying".to_string()),
+        n => Some(format!("{} changes not saved yet, retrying", n)),
     }
 }
 
-fn lists_move_up(state: &mut State) {
-    match state.lists_list_state.selected() {
-        Some(v) => {
-            let max = match v {
-                0 => None,
-                v => Some(v - 1),
+/// The due date typed into the create form, `None` when the field is empty.
+fn due_feedback(state: &State) -> Option<Result<NaiveDate, String>> {
+    let input = state.input.trim();
+    (!input.is_empty()).then(|| quickadd::parse_due(input, state.today, &state.config))
+}
+
+/// The content of a due date field: the saved date, or while it is being
+/// edited the input with what it is read as below it.
+fn due_text<'a>(state: &'a State, input_field: Option<InputField>, due_date: Option<NaiveDate>) -> Vec<Line<'a>> {
+    match input_field {
+        Some(InputField::DueDate) => {
+            let feedback = match due_feedback(state) {
+                None => Line::from("No due date".dark_gray()),
+                Some(Ok(date)) => Line::from(Span::styled(date.format("%A %Y-%m-%d").to_string(), Style::default().fg(Color::Green))),
+                Some(Err(e)) => Line::from(Span::styled(e, Style::default().fg(Color::Red))),
             };
-            state.lists_list_state.select(max);
-        }
-        None => {
-            state.lists_list_state.select(Some(0));
+            vec![Line::from(state.input.as_str()), feedback]
         }
+        _ => vec![Line::from(due_date.map(|d| d.to_string()).unwrap_or_default())],
     }
 }
 
-fn todos_move_down(state: &mut State, todos: &[Todo]) {
-    match state.todo_list_state.selected() {
-        Some(v) => {
-            state
-                .todo_list_state
-                .select(Some(min(v + 1, todos.len() - 1)));
-        }
-        None => {
-            state.todo_list_state.select(Some(0));
+/// The time of day typed into the create form, `None` when the field is empty.
+fn slot_feedback(state: &State) -> Option<Result<TimeSlot, String>> {
+    let input = state.input.trim();
+    (!input.is_empty()).then(|| TimeSlot::parse(input).ok_or_else(|| format!("Not a time of day: {}", input)))
+}
+
+/// The content of the time of day field, like `due_text`.
+fn slot_text<'a>(state: &'a State, input_field: Option<InputField>) -> Vec<Line<'a>> {
+    match input_field {
+        Some(InputField::Slot) => {
+            let feedback = match slot_feedback(state) {
+                None => Line::from("No time of day".dark_gray()),
+                Some(Ok(slot)) => Line::from(Span::styled(slot.to_string(), Style::default().fg(Color::Green))),
+                Some(Err(e)) => Line::from(Span::styled(e, Style::default().fg(Color::Red))),
+            };
+            vec![Line::from(state.input.as_str()), feedback]
         }
+        _ => vec![Line::from(state.todo_slot.map(|s| s.to_string()).unwrap_or_default())],
     }
 }
 
-fn lists_move_down(state: &mut State, lists: &Vec<TodoList>) {
-    match state.lists_list_state.selected() {
-        Some(v) => {
-            state
-                .lists_list_state
-                .select(Some(min(v + 1, lists.len() - 1)));
-        }
-        None => {
-            state.lists_list_state.select(Some(0));
-        }
+fn form_header(title: &str, state: &State) -> String {
+    match tutorial::hint(state) {
+        Some(hint) => format!("{}\n{}", title, hint),
+        None => title.to_string(),
     }
 }
 
-fn draw_create_list(
-    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    state: &State,
-    input_field: Option<InputField>,
-) {
-    terminal
-        .draw(|frame| {
-            let size = frame.size();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Length(2),
-                        Constraint::Min(5),
-                        Constraint::Length(4),
-                    ]
-                    .as_ref(),
+fn draw_onboarding(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &State, step: OnboardingStep) {
+    let (number, mut text, input_title) = match step {
+        OnboardingStep::CreateList => (
+            1,
+            vec![
+                Line::from("Todos live in lists. Start by creating your first one,"),
+                Line::from("for example Inbox, Work or Groceries."),
+            ],
+            Some("List name"),
+        ),
+        OnboardingStep::Import => (
+            2,
+            vec![
+                Line::from("Coming from your browser's reading list? Type the path to an"),
+                Line::from("exported bookmarks.html to import it into a Reading list,"),
+                Line::from("or just press enter to skip."),
+                Line::from("Later you can run: todo import --format netscape-bookmarks FILE"),
+            ],
+            Some("Bookmarks file"),
+        ),
+        OnboardingStep::Theme => (
+            3,
+            std::iter::once(Line::from("Pick a color theme, or press enter to keep the default:"))
+                .chain(
+                    THEMES
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (name, _))| Line::from(format!("({}) {}", i + 1, name))),
                 )
-                .split(size);
-
-            frame.render_widget(
-                Paragraph::new("New list")
-                    .style(Style::default())
-                    .alignment(Alignment::Center),
-                chunks[0],
-            );
+                .chain(std::iter::once(Line::from("Color rules can be tuned later in ~/.todo/config")))
+                .collect(),
+            None,
+        ),
+        OnboardingStep::Keys => (
+            4,
+            vec![
+                Line::from("The keys you need most:"),
+                Line::from("(h,j,k,l) move between and within the panes"),
+                Line::from("(N) new todo, (L) new list, (E) edit, (D) delete"),
+                Line::from("(space) complete, (v) details, (T) today, (S) stats"),
+                Line::from("(q) quit"),
+                Line::from(""),
+                Line::from("Press enter to start.".green()),
+            ],
+            None,
+        ),
+    };
+    if let Some(message) = &state.message {
+        text.insert(0, Line::from(message.as_str().yellow()));
+    }
 
-            let text = vec![
-                Line::from("(t) Input title"),
-                Line::from("(s) Save list".green().italic()),
-                Line::from("(esc) Cancel".red()),
-            ];
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(2),
+                    Constraint::Min(8),
+                    Constraint::Length(4),
+                ]
+                .as_ref(),
+            )
+            .split(size);
 
+        frame.render_widget(
+            Paragraph::new(format!("Welcome to todo-tui ({}/4), (esc) skip", number))
+                .style(Style::default().add_modifier(Modifier::BOLD))
+                .alignment(Alignment::Center),
+            chunks[0],
+        );
+        frame.render_widget(
+            Paragraph::new(text)
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            chunks[1],
+        );
+        if let Some(title) = input_title {
             frame.render_widget(
-                Paragraph::new(text.clone())
-                    .style(Style::default())
+                Paragraph::new(state.input.clone())
+                    .block(
+                        Block::default()
+                            .title(title)
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    )
+                    .style(Style::default().fg(Color::Yellow))
                     .alignment(Alignment::Center),
-                chunks[1],
+                chunks[2],
             );
+        }
+    })
+    .ok();
+}
 
-            frame.render_widget(
-                Paragraph::new(match input_field {
-                    Some(InputField::Title) => state.input.clone(),
-                    _ => state.list_title.clone(),
-                })
+fn draw_prompt(terminal: &mut Terminal<CrosstermBackend<Stdout>>, title: &str, state: &State) {
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(2), Constraint::Length(4), Constraint::Min(0)].as_ref())
+            .split(size);
+
+        frame.render_widget(
+            Paragraph::new(keymap::hint(state))
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            chunks[0],
+        );
+        frame.render_widget(
+            Paragraph::new(state.input.clone())
                 .block(
                     Block::default()
-                        .title("Title")
+                        .title(title.to_string())
                         .borders(Borders::ALL)
                         .border_type(BorderType::Rounded),
                 )
-                .style(Style::default().fg(match input_field {
-                    Some(InputField::Title) => Color::Yellow,
-                    _ => Color::White,
-                }))
+                .style(Style::default().fg(Color::Yellow))
                 .alignment(Alignment::Center),
-                chunks[2],
-            );
+            chunks[1],
+        );
+    })
+    .ok();
+}
+
+fn draw_today(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    lists: &[TodoList],
+    todos: &[Todo],
+    today: NaiveDate,
+    hint: &str,
+) {
+    let mut todos: Vec<&Todo> = todos.iter().collect();
+    todos.sort_by_key(|t| match t.slot {
+        Some(TimeSlot::At(time)) => Some(time),
+        _ => None,
+    });
+
+    let sections = [
+        ("Morning", Some(TimeSlot::Morning)),
+        ("Afternoon", Some(TimeSlot::Afternoon)),
+        ("Evening", Some(TimeSlot::Evening)),
+        ("Anytime", None),
+    ];
+    let mut text = vec![];
+    let mut due_soon: Vec<(i64, &TodoList)> = lists
+        .iter()
+        .filter_map(|list| list.days_left(today).map(|days| (days, list)))
+        .filter(|(days, _)| *days < LIST_DUE_SOON_DAYS)
+        .collect();
+    due_soon.sort_by_key(|(days, _)| *days);
+    if !due_soon.is_empty() {
+        text.push(Line::from("Lists due soon".bold()));
+        for (days, list) in due_soon {
+            text.push(Line::from(format!("  {} ({})", list.title, list_due_label(days))));
+        }
+        text.push(Line::from(""));
+    }
+    for (heading, slot) in sections {
+        let section: Vec<_> = todos
+            .iter()
+            .filter(|t| t.slot.map(|s| s.part_of_day()) == slot)
+            .collect();
+        if section.is_empty() {
+            continue;
+        }
+        text.push(Line::from(heading.bold()));
+        for todo in section {
+            let list = lists
+                .iter()
+                .find(|l| l.id == Some(todo.list_id))
+                .map(|l| l.title.clone())
+                .unwrap_or_default();
+            let time = match todo.slot {
+                Some(TimeSlot::At(time)) => time.format("%H:%M ").to_string(),
+                _ => "".to_string(),
+            };
+            text.push(Line::from(format!("  {}{} ({})", time, todo.title, list)));
+        }
+        text.push(Line::from(""));
+    }
+
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(2), Constraint::Min(5)].as_ref())
+            .split(size);
+
+        frame.render_widget(
+            Paragraph::new(hint.to_string())
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            vert_chunks[0],
+        );
+        frame.render_widget(
+            Paragraph::new(text).block(Block::default().title("Today").borders(Borders::ALL)),
+            vert_chunks[1],
+        );
+    })
+    .ok();
+}
+
+/// The due dated todos of a list with where their spans start on the
+/// timeline: their `start` custom field.
+fn get_timeline_todos(list_id: usize) -> Vec<(Todo, Option<NaiveDate>)> {
+    let starts = fetch_field_values(list_id, "start").unwrap_or_default();
+    get_todos(list_id)
+        .into_iter()
+        .filter(|todo| todo.due_date.is_some())
+        .map(|todo| {
+            let start = starts
+                .iter()
+                .find(|(id, _)| todo.id == Some(*id))
+                .and_then(|(_, value)| NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok());
+            (todo, start)
         })
-        .ok();
+        .collect()
 }
 
-fn draw_lists(
+fn draw_timeline(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    lists: &Vec<TodoList>,
-    todos: &Vec<Todo>,
-    state: &mut State,
+    list_title: &str,
+    todos: &[(Todo, Option<NaiveDate>)],
+    today: NaiveDate,
+    hint: &str,
 ) {
-    let lists_items: Vec<_> = lists
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(2), Constraint::Min(5)].as_ref())
+            .split(size);
+
+        // Titles on the left, the axis taking up the rest inside the borders.
+        let width = vert_chunks[1].width.saturating_sub(2) as usize;
+        let title_width = (width / 3).min(30);
+        let timeline = timeline::layout(todos, today, width.saturating_sub(title_width + 1));
+
+        let mut labels = vec![' '; timeline.columns];
+        let mut free_from = 0;
+        for (column, monday) in &timeline.weeks {
+            let label: Vec<char> = monday.format("%b %d").to_string().chars().collect();
+            if *column >= free_from && column + label.len() <= labels.len() {
+                labels[*column..column + label.len()].copy_from_slice(&label);
+                free_from = column + label.len() + 1;
+            }
+        }
+        let mut text = vec![Line::from(format!(
+            "{:title_width$} {}",
+            "",
+            labels.iter().collect::<String>(),
+            title_width = title_width
+        ))];
+
+        for bar in &timeline.bars {
+            let (style, due) = match (bar.completed, bar.overdue) {
+                (true, _) => (Style::default().fg(Color::DarkGray), "x"),
+                (false, true) => (Style::default().fg(Color::Red), "!"),
+                (false, false) => (Style::default().fg(Color::Cyan), "◆"),
+            };
+            // Without colors the due mark alone tells the bars apart.
+            let due = if render::colorless() { due } else { "◆" };
+            let title: String = bar.title.chars().take(title_width).collect();
+            let mut spans = vec![Span::raw(format!("{:title_width$} ", title, title_width = title_width))];
+            for column in 0..timeline.columns {
+                let start = bar.start.unwrap_or(bar.due);
+                spans.push(match column {
+                    c if c == bar.due => Span::styled(due, style),
+                    c if start <= c && c < bar.due => Span::styled("━", style),
+                    c if c == timeline.today => Span::styled("│", Style::default().fg(Color::DarkGray)),
+                    _ => Span::raw(" "),
+                });
+            }
+            text.push(Line::from(spans));
+        }
+
+        let mut load = vec![Span::raw(format!("{:title_width$} ", "Open due per week", title_width = title_width))];
+        let mut column = 0;
+        for ((start, _), count) in timeline.weeks.iter().zip(&timeline.load) {
+            if *start < column {
+                continue;
+            }
+            load.push(Span::raw(" ".repeat(start - column)));
+            let style = match *count >= timeline::CRUNCH {
+                true => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                false => Style::default(),
+            };
+            let count = match *count >= timeline::CRUNCH && render::colorless() {
+                true => format!("{}!", count),
+                false => count.to_string(),
+            };
+            column = start + count.chars().count();
+            load.push(Span::styled(count, style));
+        }
+        text.push(Line::from(""));
+        text.push(Line::from(load));
+
+        let scale = match timeline.days_per_column {
+            1 => "".to_string(),
+            days => format!(", 1 column = {} days", days),
+        };
+        frame.render_widget(
+            Paragraph::new(format!("{}, set a start=YYYY-MM-DD field to show a span", hint))
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            vert_chunks[0],
+        );
+        frame.render_widget(
+            Paragraph::new(text).block(
+                Block::default()
+                    .title(format!("Timeline: {}{}", list_title, scale))
+                    .borders(Borders::ALL),
+            ),
+            vert_chunks[1],
+        );
+    })
+    .ok();
+}
+
+fn draw_tags(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &State, index: usize, tags: &[(String, usize)]) {
+    let rules = state.config.color_rules();
+    let items: Vec<_> = tags
         .iter()
-        .map(|list| {
-            ListItem::new(Line::from(vec![Span::styled(
-                list.title.clone(),
-                Style::default(),
-            )]))
+        .map(|(name, count)| {
+            // The style todos with only this tag get.
+            let style = rules
+                .iter()
+                .filter(|rule| matches!(&rule.predicate, Predicate::Tag(tag) if model::same_tag(tag, name)))
+                .fold(Style::default().fg(Color::White), |style, rule| style.patch(rule.style));
+            let todos = if *count == 1 { "todo" } else { "todos" };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("#{}", name), style),
+                Span::styled(format!("  {} {}", count, todos), Style::default().fg(Color::DarkGray)),
+            ]))
         })
         .collect();
+    let mut cursor = Cursor::default();
+    cursor.select((!tags.is_empty()).then_some(index));
 
-    let lists_ui = List::new(lists_items)
-        .block(Block::default().title("List").borders(Borders::ALL))
-        .style(Style::default().fg(Color::White))
-        .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
-        .highlight_symbol(">>");
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(2), Constraint::Min(5)].as_ref())
+            .split(size);
+        let mut hint = vec![Line::from(keymap::hint(state))];
+        hint.extend(state.message.as_deref().map(|message| Line::from(message.yellow())));
+        frame.render_widget(Paragraph::new(hint).alignment(Alignment::Center), vert_chunks[0]);
+        let list = List::new(items)
+            .block(Block::default().title(format!("Tags ({})", tags.len())).borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+            .highlight_symbol(">>");
+        frame.render_stateful_widget(list, vert_chunks[1], cursor.list_state());
+    })
+    .ok();
+}
 
-    let todo_items: Vec<_> = todos
+fn draw_workspaces(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &State, index: usize) {
+    let workspaces = state.config.workspaces();
+    let items: Vec<_> = workspaces
         .iter()
-        .map(|todo: &Todo| {
+        .map(|(name, path)| {
+            let current = if *name == state.workspace { "* " } else { "  " };
+            let path = path.as_ref().map_or("~/.todo/todos.sqlite".to_string(), |p| p.display().to_string());
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{}{}  ", current, name)),
+                Span::styled(path, Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+    let mut cursor = Cursor::default();
+    cursor.select(Some(index));
 
-            let overdue = !todo.completed && todo.due_date.is_some() && todo.due_date.unwrap() <= Local::now().date_naive();
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(2), Constraint::Min(5)].as_ref())
+            .split(size);
+        let hint = match workspaces.len() {
+            1 => "(esc, q) back. Add workspace.<name> = <path> lines to the config for more".to_string(),
+            _ => keymap::hint(state),
+        };
+        frame.render_widget(
+            Paragraph::new(hint)
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            vert_chunks[0],
+        );
+        let list = List::new(items)
+            .block(Block::default().title("Workspaces").borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+            .highlight_symbol(">>");
+        frame.render_stateful_widget(list, vert_chunks[1], cursor.list_state());
+    })
+    .ok();
+}
 
-            ListItem::new(Line::from(vec![Span::styled(
-                format!(
-                    "{} {} {}",
-                    todo.id.or(Some(9)).expect("or is being used"),
-                    match todo.completed {
-                        true => "[x]",
-                        false => "[ ]",
-                    },
-                    todo.title.clone()
-                ),
-                Style::default().fg(match overdue { true => Color::Red, false => Color::White}),
-            )]))
+/// Applies what was typed into the prompt of the tags view to `tag`,
+/// returning the message to show and the tag to select afterwards.
+fn edit_tag(state: &mut State, tags: &[(String, usize)], tag: &str, edit: TagEdit) -> (String, String) {
+    let input = state.input.trim().trim_start_matches('#');
+    let is_tag = model::tag_of(&format!("#{}", input)) == Some(input);
+    let exists = tags.iter().any(|(name, _)| name == input);
+    let result = match edit {
+        TagEdit::Rename | TagEdit::Merge if !is_tag => Err(format!("#{} is not a tag", input)),
+        TagEdit::Rename | TagEdit::Merge if input == tag => return (String::new(), tag.to_string()),
+        TagEdit::Rename if exists => Err(format!("#{} exists, merge into it with m", input)),
+        TagEdit::Merge if !exists => Err(format!("No tag #{}", input)),
+        TagEdit::Rename => retag(tag, Some(input))
+            .map(|changed| format!("Renamed #{} to #{} in {} todos", tag, input, changed))
+            .map_err(|e| e.to_string()),
+        TagEdit::Merge => retag(tag, Some(input))
+            .map(|changed| format!("Merged #{} into #{} in {} todos", tag, input, changed))
+            .map_err(|e| e.to_string()),
+        TagEdit::Recolor if ColorRule::parse(&format!("tag:{} -> {}", tag, input)).is_none() => {
+            Err(format!("{} is not a style, try e.g. bold red", input))
+        }
+        TagEdit::Recolor => state
+            .config
+            .set_tag_color(tag, input)
+            .map(|_| format!("Styled #{} {}", tag, input))
+            .map_err(|e| e.to_string()),
+    };
+    match (result, edit) {
+        (Ok(message), TagEdit::Recolor) => {
+            state.config = Config::load();
+            (message, tag.to_string())
+        }
+        (Ok(message), _) => (message, input.to_string()),
+        (Err(message), _) => (message, tag.to_string()),
+    }
+}
+
+/// Blanks the screen down to a prompt masking the `entered` characters.
+fn draw_lock(terminal: &mut Terminal<CrosstermBackend<Stdout>>, entered: usize, message: Option<&str>) {
+    render::draw(terminal, |frame| {
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(45), Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(frame.size());
+        let lines = vec![
+            Line::from("Locked, enter the PIN"),
+            Line::from("*".repeat(entered)),
+            Line::from(Span::styled(message.unwrap_or_default().to_string(), Style::default().fg(Color::Red))),
+        ];
+        frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), vert_chunks[1]);
+    })
+    .ok();
+}
+
+fn unlock(state: &mut State, key: KeyEvent) {
+    let Some(entered) = state.locked.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Char(c) => entered.push(c),
+        KeyCode::Backspace => {
+            entered.pop();
+        }
+        KeyCode::Esc => entered.clear(),
+        KeyCode::Enter => {
+            if state.config.pin.as_ref() == Some(entered) {
+                state.locked = None;
+                state.message = None;
+            } else {
+                entered.clear();
+                // Slows down guessing.
+                thread::sleep(Duration::from_secs(1));
+                state.message = Some("Wrong PIN".to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Shown instead of the TUI while the database can't be used. Returns what
+/// fixed it, or `None` when given up on.
+fn recover(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    error: DatabaseError,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let mut error = error.to_string();
+    // The path being typed after (p).
+    let mut path: Option<String> = None;
+    loop {
+        draw_recovery(terminal, &error, path.as_deref());
+        let Some(key) = read_key()? else {
+            continue;
+        };
+        let attempt = match (path.as_mut(), key.code) {
+            (Some(input), KeyCode::Char(c)) => {
+                input.push(c);
+                continue;
+            }
+            (Some(input), KeyCode::Backspace) => {
+                input.pop();
+                continue;
+            }
+            (Some(_), KeyCode::Esc) => {
+                path = None;
+                continue;
+            }
+            (Some(input), KeyCode::Enter) => {
+                let other = config::expand_path(input.trim());
+                path = None;
+                database::use_database(Some(other.clone()));
+                Ok(format!("Using {} for this session", other.display()))
+            }
+            (None, KeyCode::Char('b')) => database::restore_latest_backup()
+                .map(|backup| format!("Restored {}", backup.display())),
+            (None, KeyCode::Char('r')) => database::repair()
+                .map(|kept| format!("Repaired the database, {} rows kept", kept)),
+            (None, KeyCode::Char('p')) => {
+                path = Some(String::new());
+                continue;
+            }
+            (None, KeyCode::Char('q') | KeyCode::Esc) => return Ok(None),
+            _ => continue,
+        };
+        match attempt.and_then(|message| database::check_db().map(|_| message)) {
+            Ok(message) => return Ok(Some(message)),
+            Err(e) => error = e.to_string(),
+        }
+    }
+}
+
+fn draw_recovery(terminal: &mut Terminal<CrosstermBackend<Stdout>>, error: &str, path: Option<&str>) {
+    let backup = match database::latest_backup() {
+        Some(backup) => format!("(b) Restore the latest backup, {}", backup.display()),
+        None => format!("(b) Restore the latest backup, none in {} yet", database::backup_dir().display()),
+    };
+    let text = vec![
+        Line::from(format!("{} can't be used:", database::get_path().display())),
+        Line::from(Span::styled(error.to_string(), Style::default().fg(Color::Red))),
+        Line::from(""),
+        Line::from(backup),
+        Line::from("(r) Repair, keeping every row that can still be read"),
+        Line::from("(p) Open a different database"),
+        Line::from("(q) Quit"),
+        Line::from(""),
+        Line::from("The damaged file is kept next to the database as .broken".dark_gray()),
+    ];
+
+    render::draw(terminal, |frame| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(2), Constraint::Min(10), Constraint::Length(4)].as_ref())
+            .split(frame.size());
+        frame.render_widget(
+            Paragraph::new("Recovery")
+                .style(Style::default().add_modifier(Modifier::BOLD))
+                .alignment(Alignment::Center),
+            chunks[0],
+        );
+        frame.render_widget(Paragraph::new(text).alignment(Alignment::Center), chunks[1]);
+        if let Some(path) = path {
+            frame.render_widget(
+                Paragraph::new(path.to_string())
+                    .block(
+                        Block::default()
+                            .title("Database path, (enter) open, (esc) cancel")
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    )
+                    .style(Style::default().fg(Color::Yellow))
+                    .alignment(Alignment::Center),
+                chunks[2],
+            );
+        }
+    })
+    .ok();
+}
+
+fn draw_stats(terminal: &mut Terminal<CrosstermBackend<Stdout>>, stats: &[ListStats], hint: &str) {
+    let rows: Vec<_> = stats
+        .iter()
+        .map(|s| {
+            Row::new(vec![
+                Cell::from(s.title.clone()),
+                Cell::from(s.open.to_string()),
+                Cell::from(s.created.to_string()),
+                Cell::from(s.completed.to_string()),
+                Cell::from(s.forecast.describe()),
+            ])
         })
         .collect();
 
-    let todo_ui = List::new(todo_items)
-        .block(Block::default().title("Todos").borders(Borders::ALL))
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["List", "Open", "Added", "Done", "Forecast"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::default()
+                .title(format!("Stats (last {} days)", WINDOW_DAYS))
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::White))
+        .widths(&[
+            Constraint::Percentage(25),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Min(20),
+        ]);
+
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(2), Constraint::Min(5)].as_ref())
+            .split(size);
+
+        frame.render_widget(
+            Paragraph::new(hint.to_string())
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            vert_chunks[0],
+        );
+        frame.render_widget(table, vert_chunks[1]);
+    })
+    .ok();
+}
+
+/// Title of the lists pane: how many lists there are, and the workspace
+/// when it isn't the default one.
+fn lists_title(lists: &[TodoList], state: &State) -> String {
+    match state.workspace.as_str() {
+        DEFAULT_WORKSPACE => format!("Lists ({})", lists.len()),
+        workspace => format!("Lists ({}) in {}", lists.len(), workspace),
+    }
+}
+
+/// Title of the todos pane: the selected list, how much is left in it and
+/// how it is ordered.
+fn todos_title(lists: &[TodoList], todos: &[Todo], headers: &[Header], state: &State) -> String {
+    let Some(list) = state.lists_cursor.get(lists) else {
+        return "Todos".to_string();
+    };
+    // Count the todos hidden in collapsed milestones too.
+    let collapsed = headers.iter().filter(|h| h.milestone.collapsed);
+    let (hidden_remaining, hidden) = collapsed.fold((0, 0), |(remaining, total), h| {
+        (remaining + h.total - h.completed, total + h.total)
+    });
+    let remaining = todos.iter().filter(|t| !t.completed).count() + hidden_remaining;
+    let title = format!("{} ({}/{} remaining, by due date)", list.title, remaining, todos.len() + hidden);
+    match state.filter.as_ref().filter(|f| Some(f.list_id) == list.id) {
+        Some(filter) => format!("{} [related: {}, (f) clear]", title, filter.label),
+        None => title,
+    }
+}
+
+/// The rows of the todos pane: the todos with their milestone headers.
+fn todo_rows<'a>(todos: &[Todo], headers: &[Header], lists: &[TodoList], state: &State) -> Vec<ListItem<'a>> {
+    let rules = state.config.color_rules();
+    let mut headers = headers.iter().peekable();
+    let mut rows = vec![];
+    for index in 0..=todos.len() {
+        while let Some(header) = headers.next_if(|h| h.index == index) {
+            let style = Style::default().add_modifier(Modifier::BOLD);
+            rows.push(ListItem::new(Line::from(Span::styled(header.label(), style))));
+        }
+        if let Some(todo) = todos.get(index) {
+            let pending = todo.id.is_some_and(|id| state.queue.is_pending(id));
+            rows.push(todo_item(todo, lists, &rules, state.today, pending));
+        }
+    }
+    rows
+}
+
+/// Lists due within this many days are highlighted and shown in the today view.
+const LIST_DUE_SOON_DAYS: i64 = 7;
+
+fn list_due_label(days_left: i64) -> String {
+    match days_left {
+        0 => "due today".to_string(),
+        1 => "1 day left".to_string(),
+        -1 => "1 day overdue".to_string(),
+        days if days < 0 => format!("{} days overdue", -days),
+        days => format!("{} days left", days),
+    }
+}
+
+fn list_item<'a>(list: &TodoList, today: NaiveDate) -> ListItem<'a> {
+    let mut spans = vec![Span::raw(list.title.clone())];
+    if let Some(days_left) = list.days_left(today) {
+        let color = match days_left {
+            days if days < 0 => Color::Red,
+            days if days < LIST_DUE_SOON_DAYS => Color::Yellow,
+            _ => Color::DarkGray,
+        };
+        spans.push(Span::styled(format!(" ({})", list_due_label(days_left)), Style::default().fg(color)));
+    }
+    ListItem::new(Line::from(spans))
+}
+
+/// `pending` marks a todo with changes waiting in the queue, which the row
+/// doesn't show yet.
+fn todo_item<'a>(todo: &Todo, lists: &[TodoList], rules: &[ColorRule], today: NaiveDate, pending: bool) -> ListItem<'a> {
+    let list_title = lists
+        .iter()
+        .find(|l| l.id == Some(todo.list_id))
+        .map(|l| l.title.as_str())
+        .unwrap_or_default();
+    let marker = rules::marker_for(rules, todo, list_title, today).filter(|_| render::colorless());
+    let mut spans = vec![Span::styled(
+        format!(
+            "{} {} {}{}",
+            todo.id.or(Some(9)).expect("or is being used"),
+            match todo.completed {
+                true => "[x]",
+                false => "[ ]",
+            },
+            todo.priority.marker(),
+            todo.title.clone()
+        ),
+        rules::style_for(rules, todo, list_title, today),
+    )];
+    if let Some(marker) = marker {
+        spans.push(Span::raw(format!(" ({})", marker)));
+    }
+    if pending {
+        spans.push(Span::styled(" (pending)", Style::default().fg(Color::DarkGray)));
+    }
+    ListItem::new(Line::from(spans))
+}
+
+fn draw_lists(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    lists: &[TodoList],
+    todos: &[Todo],
+    headers: &[Header],
+    state: &mut State,
+) {
+    let lists_items: Vec<_> = lists.iter().map(|list| list_item(list, state.today)).collect();
+
+    let lists_ui = List::new(lists_items)
+        .block(Block::default().title(lists_title(lists, state)).borders(Borders::ALL))
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
         .highlight_symbol(">>");
 
-    terminal
-        .draw(|frame| {
-            let size = frame.size();
-            let vert_chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Length(2),
-                        Constraint::Min(20),
-                    ]
-                    .as_ref(),
-                )
-                .split(size);
-
-            let list_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Percentage(30),
-                        Constraint::Min(20),
-                    ]
-                    .as_ref(),
-                )
-                .split(vert_chunks[1]);
+    let todo_ui = List::new(todo_rows(todos, headers, lists, state))
+        .block(Block::default().title(todos_title(lists, todos, headers, state)).borders(Borders::ALL))
+        .style(Style::default().fg(Color::White))
+        .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+        .highlight_symbol(">>");
 
-            frame.render_widget(
-                Paragraph::new("(N) new task, (L) new list, (h,j,k,l) move, (D) delete, (esc, q) exit")
-                    .style(Style::default())
-                    .alignment(Alignment::Center),
-                vert_chunks[0],
-            );
-            frame.render_stateful_widget(lists_ui, list_chunks[0], &mut state.lists_list_state);
-            frame.render_stateful_widget(todo_ui, list_chunks[1], &mut state.todo_list_state);
-        })
-        .ok();
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(2),
+                    Constraint::Min(20),
+                    Constraint::Length(2),
+                ]
+                .as_ref(),
+            )
+            .split(size);
+
+        let list_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Percentage(30),
+                    Constraint::Min(20),
+                ]
+                .as_ref(),
+            )
+            .split(vert_chunks[1]);
+
+        frame.render_widget(
+            Paragraph::new(header_text(state))
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            vert_chunks[0],
+        );
+        frame.render_stateful_widget(lists_ui, list_chunks[0], state.lists_cursor.list_state());
+        let todos_state = state.todos_cursor.rows_state(|index| milestones::row_of(headers, index));
+        frame.render_stateful_widget(todo_ui, list_chunks[1], todos_state);
+        frame.render_widget(hint_bar(state), vert_chunks[2]);
+    })
+    .ok();
 }
 
 
 fn draw_lists_with_details(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    lists: &Vec<TodoList>,
-    todos: &Vec<Todo>,
+    lists: &[TodoList],
+    todos: &[Todo],
+    headers: &[Header],
     state: &mut State,
-    details_index: usize
+    details_index: usize,
+    details: &Details,
 ) {
-    let lists_items: Vec<_> = lists
-        .iter()
-        .map(|list| {
-            ListItem::new(Line::from(vec![Span::styled(
-                list.title.clone(),
-                Style::default(),
-            )]))
-        })
-        .collect();
+    let lists_items: Vec<_> = lists.iter().map(|list| list_item(list, state.today)).collect();
 
     let lists_ui = List::new(lists_items)
-        .block(Block::default().title("List").borders(Borders::ALL))
+        .block(Block::default().title(lists_title(lists, state)).borders(Borders::ALL))
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
         .highlight_symbol(">>");
 
-    let todo_items: Vec<_> = todos
-        .iter()
-        .map(|todo| {
-            ListItem::new(Line::from(vec![Span::styled(
-                format!(
-                    "{} {} {}",
-                    todo.id.or(Some(9)).expect("or is being used"),
-                    match todo.completed {
-                        true => "[x]",
-                        false => "[ ]",
-                    },
-                    todo.title.clone()
-                ),
-                Style::default(),
-            )]))
-        })
-        .collect();
-
-    let todo_ui = List::new(todo_items)
-        .block(Block::default().title("Todos").borders(Borders::ALL))
+    let todo_ui = List::new(todo_rows(todos, headers, lists, state))
+        .block(Block::default().title(todos_title(lists, todos, headers, state)).borders(Borders::ALL))
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
         .highlight_symbol(">>");
 
-    terminal
-        .draw(|frame| {
-            let size = frame.size();
-            let vert_chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Length(2),
-                        Constraint::Min(20),
-                        Constraint::Length(2),
-                        Constraint::Length(4),
-                    ]
-                    .as_ref(),
-                )
-                .split(size);
-
-            let list_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Percentage(30),
-                        Constraint::Min(20),
-                    ]
-                    .as_ref(),
-                )
-                .split(vert_chunks[1]);
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let vert_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(2),
+                    Constraint::Min(20),
+                    Constraint::Length(2),
+                    Constraint::Length(4),
+                    Constraint::Length(4),
+                    Constraint::Length(2),
+                ]
+                .as_ref(),
+            )
+            .split(size);
 
-            frame.render_widget(
-                Paragraph::new("(N) new task, (L) new list, (h,j,k,l) move, (D) delete, (esc, q) exit")
-                    .style(Style::default())
-                    .alignment(Alignment::Center),
-                vert_chunks[0],
-            );
-            frame.render_stateful_widget(lists_ui, list_chunks[0], &mut state.lists_list_state);
-            frame.render_stateful_widget(todo_ui, list_chunks[1], &mut state.todo_list_state);
+        let list_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Percentage(30),
+                    Constraint::Min(20),
+                ]
+                .as_ref(),
+            )
+            .split(vert_chunks[1]);
 
-            let selected_todo = todos.get(details_index);
-            match selected_todo {
-                Some(v) => {
+        frame.render_widget(
+            Paragraph::new(header_text(state))
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            vert_chunks[0],
+        );
+        frame.render_stateful_widget(lists_ui, list_chunks[0], state.lists_cursor.list_state());
+        let todos_state = state.todos_cursor.rows_state(|index| milestones::row_of(headers, index));
+        frame.render_stateful_widget(todo_ui, list_chunks[1], todos_state);
 
+        let selected_todo = todos.get(details_index);
+        if let Some(v) = selected_todo {
+            let mut description = vec![Line::from(v.description.clone().unwrap_or_default())];
+            description.extend(details.attachments.iter().map(|url| Line::from(url.as_str().underlined())));
+            description.extend(details.fields.iter().map(|(key, value)| Line::from(format!("{}: {}", key, value))));
+            if v.priority != Priority::Normal {
+                description.push(Line::from(format!("Priority: {}", v.priority)));
+            }
+            if let Some(completed_at) = v.completed_at.filter(|_| v.completed) {
+                let completed = format!("Completed {}", completed_at.format("%Y-%m-%d %H:%M"));
+                description.push(Line::from(Span::styled(completed, Style::default().fg(Color::DarkGray))));
+            }
             frame.render_widget(
                 Paragraph::new(v.title.clone())
                     .style(Style::default())
@@ -741,16 +2780,31 @@ fn draw_lists_with_details(
                 vert_chunks[2],
             );
             frame.render_widget(
-                Paragraph::new(v.description.clone().or(Some("".to_string())).expect("or"))
+                Paragraph::new(description)
                     .style(Style::default())
                     .alignment(Alignment::Center),
                 vert_chunks[3],
             );
-                },
-                None =>{}
-            };
-        })
-        .ok();
+        }
+
+        if !details.links.is_empty() {
+            let mut text = vec![Line::from("See also (g) jump, (b) back".bold())];
+            text.extend(
+                details
+                    .links
+                    .iter()
+                    .map(|(kind, todo)| Line::from(format!("{}: {} {}", kind, todo.id.unwrap_or(0), todo.title))),
+            );
+            frame.render_widget(
+                Paragraph::new(text)
+                    .style(Style::default())
+                    .alignment(Alignment::Center),
+                vert_chunks[4],
+            );
+        }
+        frame.render_widget(hint_bar(state), vert_chunks[5]);
+    })
+    .ok();
 }
 
 fn draw_create_todo(
@@ -758,103 +2812,121 @@ fn draw_create_todo(
     state: &State,
     input_field: Option<InputField>,
 ) {
-    terminal
-        .draw(|frame| {
-            let size = frame.size();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Min(2),
-                        Constraint::Min(5),
-                        Constraint::Length(4),
-                        Constraint::Length(4),
-                        Constraint::Length(4),
-                    ]
-                    .as_ref(),
-                )
-                .split(size);
+    render::draw(terminal, |frame| {
+        let size = frame.size();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Min(2),
+                    Constraint::Min(5),
+                    Constraint::Length(4),
+                    Constraint::Length(4),
+                    Constraint::Length(4),
+                    Constraint::Length(4),
+                    Constraint::Length(2),
+                ]
+                .as_ref(),
+            )
+            .split(size);
 
-            frame.render_widget(
-                Paragraph::new("New todo")
-                    .style(Style::default())
-                    .alignment(Alignment::Center),
-                chunks[0],
-            );
+        frame.render_widget(
+            Paragraph::new(form_header("New todo", state))
+                .style(Style::default())
+                .alignment(Alignment::Center),
+            chunks[0],
+        );
 
-            let text = vec![
-                Line::from("Create a todo"),
-                Line::from("(t) Input title"),
-                Line::from("(d) Input description"),
-                Line::from("(D) Input due date"),
-                Line::from("(s) Save todo".green().italic()),
-                Line::from("(esc) Cancel".red()),
-            ];
+        let text = vec![
+            Line::from("Create a todo"),
+            Line::from("(t) Input title"),
+            Line::from("(d) Input description"),
+            Line::from("(D) Input due date"),
+            Line::from("(T) Input time of day"),
+            Line::from("(s) Save todo".green().italic()),
+            Line::from("(esc) Cancel".red()),
+        ];
 
-            frame.render_widget(
-                Paragraph::new(text.clone())
-                    .style(Style::default())
-                    .alignment(Alignment::Center),
-                chunks[1],
-            );
-
-            frame.render_widget(
-                Paragraph::new(match input_field {
-                    Some(InputField::Title) => state.input.clone(),
-                    _ => state.todo_title.clone(),
-                })
-                .block(
-                    Block::default()
-                        .title("Title")
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Rounded),
-                )
-                .style(Style::default().fg(match input_field {
-                    Some(InputField::Title) => Color::Yellow,
-                    _ => Color::White,
-                }))
+        frame.render_widget(
+            Paragraph::new(text.clone())
+                .style(Style::default())
                 .alignment(Alignment::Center),
-                chunks[2],
-            );
+            chunks[1],
+        );
 
-            frame.render_widget(
-                Paragraph::new(match input_field {
-                    Some(InputField::Description) => state.input.clone(),
-                    _ => state.todo_description.clone(),
-                })
-                .block(
-                    Block::default()
-                        .title("Description")
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Rounded),
-                )
-                .style(Style::default().fg(match input_field {
-                    Some(InputField::Description) => Color::Yellow,
-                    _ => Color::White,
-                }))
-                .alignment(Alignment::Center),
-                chunks[3],
-            );
-            
-            frame.render_widget(
-                Paragraph::new(match input_field {
-                    Some(InputField::DueDate) => state.input.clone(),
-                    _ => match state.todo_due_date.clone(){ None => "".to_string(), Some(v) => v.to_string()},
-                })
-                .block(
-                    Block::default()
-                        .title("Due date +days from now")
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Rounded),
-                )
-                .style(Style::default().fg(match input_field {
-                    Some(InputField::DueDate) => Color::Yellow,
-                    _ => Color::White,
-                }))
-                .alignment(Alignment::Center),
-                chunks[4],
-            );
-        })
-        .ok();
+        frame.render_widget(
+            Paragraph::new(match input_field {
+                Some(InputField::Title) => state.input.clone(),
+                _ => state.todo_title.clone(),
+            })
+            .block(
+                Block::default()
+                    .title("Title")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(match input_field {
+                Some(InputField::Title) => Color::Yellow,
+                _ => Color::White,
+            }))
+            .alignment(Alignment::Center),
+            chunks[2],
+        );
+
+        frame.render_widget(
+            Paragraph::new(match input_field {
+                Some(InputField::Description) => state.input.clone(),
+                _ => state.todo_description.clone(),
+            })
+            .block(
+                Block::default()
+                    .title("Description")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(match input_field {
+                Some(InputField::Description) => Color::Yellow,
+                _ => Color::White,
+            }))
+            .alignment(Alignment::Center),
+            chunks[3],
+        );
+        
+        frame.render_widget(
+            Paragraph::new(due_text(state, input_field, state.todo_due_date))
+            .block(
+                Block::default()
+                    .title("Due date (YYYY-MM-DD, +days, today or tomorrow)")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(match input_field {
+                Some(InputField::DueDate) => Color::Yellow,
+                _ => Color::White,
+            }))
+            .alignment(Alignment::Center),
+            chunks[4],
+        );
+
+        frame.render_widget(
+            Paragraph::new(slot_text(state, input_field))
+            .block(
+                Block::default()
+                    .title("Time of day (morning, afternoon, evening or HH:MM)")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(match input_field {
+                Some(InputField::Slot) => Color::Yellow,
+                _ => Color::White,
+            }))
+            .alignment(Alignment::Center),
+            chunks[5],
+        );
+        if keymap::mode(state) == Some(keymap::Mode::Field) {
+            frame.render_widget(hint_bar(state), chunks[6]);
+        }
+    })
+    .ok();
 }
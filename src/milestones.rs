@@ -0,0 +1,54 @@
+use crate::model::{Milestone, Todo};
+
+/// A milestone's section header in the todos pane.
+pub struct Header {
+    pub milestone: Milestone,
+    /// The index of the first visible todo after the header. Collapsed
+    /// milestones have no visible todos, so several headers can share it.
+    pub index: usize,
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl Header {
+    pub fn label(&self) -> String {
+        let fold = if self.milestone.collapsed { "▸" } else { "▾" };
+        format!("{} {} ({}/{} done)", fold, self.milestone.title, self.completed, self.total)
+    }
+}
+
+/// Orders `todos` by milestone, keeping their order within each milestone,
+/// and drops those in collapsed milestones. Todos without a milestone, or
+/// with one of another list, come first under no header.
+pub fn group(todos: Vec<Todo>, milestones: Vec<Milestone>) -> (Vec<Todo>, Vec<Header>) {
+    let (mut visible, mut rest): (Vec<Todo>, Vec<Todo>) = todos
+        .into_iter()
+        .partition(|todo| !milestones.iter().any(|m| m.id.is_some() && m.id == todo.milestone_id));
+
+    let mut headers = vec![];
+    for milestone in milestones {
+        let (section, others) = rest.into_iter().partition::<Vec<Todo>, _>(|todo| todo.milestone_id == milestone.id);
+        rest = others;
+        let header = Header {
+            index: visible.len(),
+            completed: section.iter().filter(|t| t.completed).count(),
+            total: section.len(),
+            milestone,
+        };
+        if !header.milestone.collapsed {
+            visible.extend(section);
+        }
+        headers.push(header);
+    }
+    (visible, headers)
+}
+
+/// The position among `headers` of the milestone `todo` is in.
+pub fn position_of(headers: &[Header], todo: &Todo) -> Option<usize> {
+    headers.iter().position(|h| h.milestone.id.is_some() && h.milestone.id == todo.milestone_id)
+}
+
+/// The row of the todo at `index` once the headers are shown above it.
+pub fn row_of(headers: &[Header], index: usize) -> usize {
+    index + headers.iter().filter(|h| h.index <= index).count()
+}
@@ -1,8 +1,8 @@
-use chrono::NaiveDate;
-use std::vec::Vec;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use std::{fmt, vec::Vec};
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Todo {
     pub id: Option<usize>,
     pub list_id: usize,
@@ -10,13 +10,262 @@ pub struct Todo {
     pub description: Option<String>,
     pub due_date: Option<NaiveDate>,
     pub completed: bool,
-    pub completed_date: Option<NaiveDate>,
+    pub created_date: Option<NaiveDate>,
+    pub slot: Option<TimeSlot>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub priority: Priority,
+    /// The milestone of the todo's list it is grouped under, if any.
+    pub milestone_id: Option<usize>,
+    #[allow(dead_code)]
     pub dependencies: Vec<usize>,
 }
 
+impl Todo {
+    /// A new, open todo in the list with nothing else set yet. Fill in the
+    /// rest with struct update syntax: `Todo { due_date, ..Todo::new(list_id, title) }`.
+    pub fn new(list_id: usize, title: String) -> Todo {
+        Todo {
+            id: None,
+            list_id,
+            title,
+            description: None,
+            due_date: None,
+            completed: false,
+            created_date: None,
+            slot: None,
+            completed_at: None,
+            priority: Priority::Normal,
+            milestone_id: None,
+            dependencies: vec![],
+        }
+    }
+
+    /// Words starting with `#` in the title and description, without the `#`.
+    pub fn tags(&self) -> Vec<String> {
+        let description = self.description.as_deref().unwrap_or_default();
+        self.title
+            .split_whitespace()
+            .chain(description.split_whitespace())
+            .filter_map(tag_of)
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        has_tag(&self.title, tag) || self.description.as_deref().is_some_and(|d| has_tag(d, tag))
+    }
+
+    /// How pressing the todo is on `today`, higher first: its priority, plus
+    /// a point a day over the week before it is due and the week after.
+    /// Plugins can score it differently, see `plugin::Plugins::urgency`.
+    pub fn urgency(&self, today: NaiveDate) -> f64 {
+        let priority = match self.priority {
+            Priority::Normal => 0.0,
+            Priority::High => 4.0,
+            Priority::Urgent => 8.0,
+        };
+        let due = match self.due_date {
+            Some(date) => (7 - (date - today).num_days()).clamp(0, 14) as f64,
+            None => 0.0,
+        };
+        priority + due
+    }
+}
+
+/// Whether `c` can end a tag, anything else after a tag is punctuation.
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// The tag a word is, without the `#` and any punctuation after it.
+pub fn tag_of(word: &str) -> Option<&str> {
+    let tag = word.strip_prefix('#')?.trim_end_matches(|c: char| !is_tag_char(c));
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Whether two tags are the same one. Case doesn't matter, in any script:
+/// `#Work` is `#work` and `#CAFÉ` is `#café`.
+pub fn same_tag(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Whether `text` has `#tag` in it, see `tag_of` and `same_tag`. The
+/// database matches tags with this too, so queries agree with the TUI.
+pub fn has_tag(text: &str, tag: &str) -> bool {
+    text.split_whitespace().filter_map(tag_of).any(|t| same_tag(t, tag))
+}
+
+/// `text` with every `#from` renamed to `#to`, or removed when `to` is
+/// `None`. Punctuation after the tag is kept.
+pub fn retag(text: &str, from: &str, to: Option<&str>) -> String {
+    let mut out = String::new();
+    for piece in text.split_inclusive(char::is_whitespace) {
+        let word = piece.trim_end_matches(char::is_whitespace);
+        let Some(tag) = tag_of(word).filter(|tag| *tag == from) else {
+            out.push_str(piece);
+            continue;
+        };
+        let rest = &piece[1 + tag.len()..];
+        match to {
+            Some(to) => {
+                out.push('#');
+                out.push_str(to);
+                out.push_str(rest);
+            }
+            // A bare tag goes with the space after it.
+            None if word.len() == 1 + tag.len() => {}
+            None => out.push_str(rest),
+        }
+    }
+    match to {
+        Some(_) => out,
+        None => out.trim_end().to_string(),
+    }
+}
+
+/// A free-form link between two todos, e.g. "related" or "duplicates".
+#[derive(Debug, Clone)]
+pub struct Relation {
+    pub todo_id: usize,
+    pub related_id: usize,
+    pub kind: String,
+}
+
+/// A named group of todos within a list, shown as a section header.
+#[derive(Debug, Clone)]
+pub struct Milestone {
+    pub id: Option<usize>,
+    pub title: String,
+    /// Milestones are shown in ascending position.
+    pub position: usize,
+    /// A collapsed milestone shows its header but hides its todos.
+    pub collapsed: bool,
+}
+
+/// What changed in the todos table since a sync cursor, see
+/// `database::fetch_changes`.
+#[derive(Debug)]
+pub struct Delta {
+    /// Pass this as the cursor next time to get only later changes.
+    pub cursor: i64,
+    /// Todos added or changed, as they are now.
+    pub todos: Vec<Todo>,
+    /// Ids of deleted todos.
+    pub deleted: Vec<usize>,
+}
+
 #[derive(Debug)]
 pub struct TodoList {
     pub id: Option<usize>,
     pub title: String,
+    /// A deadline for the list as a whole, for project style lists.
+    pub due_date: Option<NaiveDate>,
+}
+
+impl TodoList {
+    /// Days until the list is due, negative once it is overdue.
+    pub fn days_left(&self, today: NaiveDate) -> Option<i64> {
+        self.due_date.map(|due| (due - today).num_days())
+    }
 }
 
+
+/// How pressing a todo is. Overdue todos can be raised automatically, see
+/// `Config::escalate_after`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn parse(value: &str) -> Option<Priority> {
+        match value.trim().to_lowercase().as_str() {
+            "normal" | "n" => Some(Priority::Normal),
+            "high" | "h" => Some(Priority::High),
+            "urgent" | "u" => Some(Priority::Urgent),
+            _ => None,
+        }
+    }
+
+    /// How the priority is stored in the database.
+    pub fn level(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_level(level: u8) -> Priority {
+        match level {
+            0 => Priority::Normal,
+            1 => Priority::High,
+            _ => Priority::Urgent,
+        }
+    }
+
+    /// The next priority, wrapping around from urgent to normal.
+    pub fn cycled(&self) -> Priority {
+        Priority::from_level((self.level() + 1) % 3)
+    }
+
+    /// Shown in front of the title in lists.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Priority::Normal => "",
+            Priority::High => "! ",
+            Priority::Urgent => "!! ",
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Priority::Normal => write!(f, "normal"),
+            Priority::High => write!(f, "high"),
+            Priority::Urgent => write!(f, "urgent"),
+        }
+    }
+}
+
+/// When during the day a todo is planned, either a rough part of the day or
+/// an explicit time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSlot {
+    Morning,
+    Afternoon,
+    Evening,
+    At(NaiveTime),
+}
+
+impl TimeSlot {
+    pub fn parse(value: &str) -> Option<TimeSlot> {
+        match value.trim().to_lowercase().as_str() {
+            "morning" | "m" => Some(TimeSlot::Morning),
+            "afternoon" | "a" => Some(TimeSlot::Afternoon),
+            "evening" | "e" => Some(TimeSlot::Evening),
+            other => NaiveTime::parse_from_str(other, "%H:%M").ok().map(TimeSlot::At),
+        }
+    }
+
+    /// The part of the day an explicit time falls into.
+    pub fn part_of_day(&self) -> TimeSlot {
+        match self {
+            TimeSlot::At(time) if time.hour() < 12 => TimeSlot::Morning,
+            TimeSlot::At(time) if time.hour() < 17 => TimeSlot::Afternoon,
+            TimeSlot::At(_) => TimeSlot::Evening,
+            slot => *slot,
+        }
+    }
+}
+
+impl fmt::Display for TimeSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeSlot::Morning => write!(f, "morning"),
+            TimeSlot::Afternoon => write!(f, "afternoon"),
+            TimeSlot::Evening => write!(f, "evening"),
+            TimeSlot::At(time) => write!(f, "{}", time.format("%H:%M")),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    cell::RefCell,
+    env,
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    rc::Rc,
+};
+
+use chrono::{Local, NaiveDate};
+use rhai::{module_resolvers::FileModuleResolver, Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, AST};
+
+use crate::{
+    config::Config,
+    database::{
+        self, fetch_lists, fetch_plugin_keys, fetch_plugin_value, fetch_todo, fetch_todos, remove_plugin_value,
+        set_plugin_value, todo_dir, toggle_todo_completion,
+    },
+    model::Todo,
+    query::TodoQuery,
+    quickadd::QuickAdd,
+};
+
+/// Scripts can't run forever: a key press waits for them.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Plugins live in `~/.todo/plugins/`. Rhai scripts (`*.rhai`) run inside
+/// todo, see `Plugin`. Any other executable is a command of its own that
+/// gets the `todo` binary in `TODO_BIN` and the workspace in use in
+/// `TODO_WORKSPACE`, and is expected to change todos through its commands.
+pub fn plugin_dir() -> PathBuf {
+    todo_dir().join("plugins")
+}
+
+fn is_script(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "rhai")
+}
+
+/// A Rhai script from the plugin dir, named after its file. Running the
+/// script registers what it adds to todo:
+///
+/// ```text
+/// command("standup", "Print yesterday's done todos", |args| { ... });
+/// urgency(|todo, score| if "waiting" in todo.tags { score - 5 } else { score });
+/// key("x", "export", |todo| { ... });
+/// ```
+///
+/// Todos are maps of `id`, `list_id`, `title`, `description`, `due`,
+/// `days_left`, `priority`, `completed` and `tags`. Scripts reach them
+/// through `todos()`, `todos(list)`, `todo(id)`, `add(line)` (quick-add
+/// syntax) and `complete(id)`, and keep their own data with `store_get`,
+/// `store_set`, `store_remove` and `store_keys`. Every plugin only sees its
+/// own keys, and Rhai has no access to files, processes or the network.
+pub struct Plugin {
+    pub name: String,
+    engine: Engine,
+    ast: AST,
+    hooks: Rc<RefCell<Hooks>>,
+    /// What the script printed since it was last asked.
+    output: Rc<RefCell<Vec<String>>>,
+}
+
+#[derive(Default)]
+struct Hooks {
+    /// `(name, help, function)`
+    commands: Vec<(String, String, FnPtr)>,
+    urgency: Option<FnPtr>,
+    /// `(key, label, function)`
+    keys: Vec<(char, String, FnPtr)>,
+}
+
+impl Plugin {
+    fn load(path: &Path) -> Result<Plugin, Box<dyn Error>> {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let script = fs::read_to_string(path)?;
+        Plugin::compile(&name, &script).map_err(|e| failed(&name, e))
+    }
+
+    fn compile(name: &str, script: &str) -> Result<Plugin, Box<dyn Error>> {
+        let hooks = Rc::new(RefCell::new(Hooks::default()));
+        let output = Rc::new(RefCell::new(vec![]));
+        let engine = engine(name, &hooks, &output);
+        let ast = engine.compile(script)?;
+        engine.run_ast(&ast)?;
+        Ok(Plugin { name: name.to_string(), engine, ast, hooks, output })
+    }
+
+    /// The commands the script registered, as `(name, help)`.
+    pub fn commands(&self) -> Vec<(String, String)> {
+        self.hooks.borrow().commands.iter().map(|(name, help, _)| (name.clone(), help.clone())).collect()
+    }
+
+    /// The keys the script bound in the list view, as `(key, label)`.
+    pub fn keys(&self) -> Vec<(char, String)> {
+        self.hooks.borrow().keys.iter().map(|(key, label, _)| (*key, label.clone())).collect()
+    }
+
+    pub fn scores_urgency(&self) -> bool {
+        self.hooks.borrow().urgency.is_some()
+    }
+
+    // Hooks are cloned out before the call, which may register more of them.
+    fn call(&self, function: FnPtr, args: impl rhai::FuncArgs) -> Result<Dynamic, Box<dyn Error>> {
+        function.call::<Dynamic>(&self.engine, &self.ast, args).map_err(|e| failed(&self.name, e))
+    }
+
+    fn take_output(&self) -> Vec<String> {
+        self.output.borrow_mut().drain(..).collect()
+    }
+}
+
+/// The script plugins in the plugin dir, in file name order.
+#[derive(Default)]
+pub struct Plugins {
+    pub plugins: Vec<Plugin>,
+    /// Scripts that could not be loaded, one message each.
+    pub errors: Vec<String>,
+}
+
+impl Plugins {
+    pub fn load() -> Plugins {
+        let mut plugins = Plugins::default();
+        let Ok(entries) = fs::read_dir(plugin_dir()) else {
+            return plugins;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|path| path.is_file() && is_script(path))
+            .collect();
+        paths.sort();
+        for path in paths {
+            match Plugin::load(&path) {
+                Ok(plugin) => plugins.plugins.push(plugin),
+                Err(e) => plugins.errors.push(e.to_string()),
+            }
+        }
+        plugins
+    }
+
+    /// Runs the first script command called `name` and returns what it
+    /// printed followed by its result, `None` if no script has the command.
+    pub fn run_command(&self, name: &str, args: &[String]) -> Option<Result<Vec<String>, Box<dyn Error>>> {
+        self.plugins.iter().find_map(|plugin| {
+            let hooks = plugin.hooks.borrow();
+            let function = hooks.commands.iter().find(|(command, ..)| command == name)?.2.clone();
+            drop(hooks);
+            let args: Array = args.iter().map(|arg| Dynamic::from(arg.clone())).collect();
+            Some(plugin.call(function, (args,)).map(|result| {
+                let mut lines = plugin.take_output();
+                if !result.is_unit() {
+                    lines.push(result.to_string());
+                }
+                lines
+            }))
+        })
+    }
+
+    /// The todo's `Todo::urgency`, passed through the urgency hook of every
+    /// script that has one, in order.
+    pub fn urgency(&self, todo: &Todo, today: NaiveDate) -> Result<f64, Box<dyn Error>> {
+        let mut score = todo.urgency(today);
+        for plugin in &self.plugins {
+            let Some(function) = plugin.hooks.borrow().urgency.clone() else {
+                continue;
+            };
+            let result = plugin.call(function, (todo_map(todo, today), score))?;
+            score = result
+                .as_float()
+                .or_else(|_| result.as_int().map(|i| i as f64))
+                .map_err(|_| failed(&plugin.name, "urgency has to be a number"))?;
+        }
+        Ok(score)
+    }
+
+    /// The keys bound by scripts, as `(key, label)`. The first script to bind
+    /// a key gets it.
+    pub fn keys(&self) -> Vec<(char, String)> {
+        let mut keys: Vec<(char, String)> = vec![];
+        for key in self.plugins.iter().flat_map(Plugin::keys) {
+            if !keys.iter().any(|(bound, _)| *bound == key.0) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// Runs what a script bound to `key` with the selected todo, if there is
+    /// one, and returns a line to show for it. `None` if no script bound it.
+    pub fn run_key(&self, key: char, todo: Option<&Todo>) -> Option<Result<String, Box<dyn Error>>> {
+        self.plugins.iter().find_map(|plugin| {
+            let hooks = plugin.hooks.borrow();
+            let (_, label, function) = hooks.keys.iter().find(|(bound, ..)| *bound == key)?.clone();
+            drop(hooks);
+            let today = Local::now().date_naive();
+            let todo = todo.map_or(Dynamic::UNIT, |todo| todo_map(todo, today));
+            Some(plugin.call(function, (todo,)).map(|result| {
+                let printed = plugin.take_output().pop();
+                let line = match result.is_unit() {
+                    true => printed.unwrap_or_else(|| "done".to_string()),
+                    false => result.to_string(),
+                };
+                format!("{}: {}", label, line)
+            }))
+        })
+    }
+}
+
+/// An engine for the plugin `name`, with the functions its script can call.
+fn engine(name: &str, hooks: &Rc<RefCell<Hooks>>, output: &Rc<RefCell<Vec<String>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(100_000);
+    engine.set_max_map_size(100_000);
+    // `import` only finds other scripts in the plugin dir.
+    engine.set_module_resolver(FileModuleResolver::new_with_path(plugin_dir()));
+    // Printing straight to the terminal would garble the TUI.
+    let printed = output.clone();
+    engine.on_print(move |line| printed.borrow_mut().push(line.to_string()));
+    let printed = output.clone();
+    engine.on_debug(move |line, _, _| printed.borrow_mut().push(line.to_string()));
+
+    let registered = hooks.clone();
+    engine.register_fn("command", move |name: &str, help: &str, function: FnPtr| {
+        registered.borrow_mut().commands.push((name.to_string(), help.to_string(), function));
+    });
+    let registered = hooks.clone();
+    engine.register_fn("urgency", move |function: FnPtr| {
+        registered.borrow_mut().urgency = Some(function);
+    });
+    let registered = hooks.clone();
+    engine.register_fn("key", move |key: &str, label: &str, function: FnPtr| -> Result<(), Box<EvalAltResult>> {
+        let mut chars = key.chars();
+        let (Some(key), None) = (chars.next(), chars.next()) else {
+            return Err(format!("A key is a single character, not {:?}", key).into());
+        };
+        registered.borrow_mut().keys.push((key, label.to_string(), function));
+        Ok(())
+    });
+
+    let plugin = name.to_string();
+    engine.register_fn("store_get", move |key: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+        let value = fetch_plugin_value(&plugin, key).map_err(script_error)?;
+        Ok(value.map_or(Dynamic::UNIT, Dynamic::from))
+    });
+    let plugin = name.to_string();
+    engine.register_fn("store_set", move |key: &str, value: Dynamic| -> Result<(), Box<EvalAltResult>> {
+        set_plugin_value(&plugin, key, &value.to_string()).map_err(script_error)
+    });
+    let plugin = name.to_string();
+    engine.register_fn("store_remove", move |key: &str| -> Result<(), Box<EvalAltResult>> {
+        remove_plugin_value(&plugin, key).map_err(script_error)
+    });
+    let plugin = name.to_string();
+    engine.register_fn("store_keys", move || -> Result<Array, Box<EvalAltResult>> {
+        let keys = fetch_plugin_keys(&plugin).map_err(script_error)?;
+        Ok(keys.into_iter().map(Dynamic::from).collect())
+    });
+
+    engine.register_fn("todos", || open_todos(TodoQuery::new()));
+    engine.register_fn("todos", |list: &str| -> Result<Array, Box<EvalAltResult>> {
+        let lists = fetch_lists().map_err(script_error)?;
+        match lists.iter().find(|l| l.title == list) {
+            Some(found) => open_todos(TodoQuery::new().list(found.id.expect("Id exists"))),
+            None => Err(format!("No list named {}", list).into()),
+        }
+    });
+    engine.register_fn("todo", |id: i64| -> Result<Dynamic, Box<EvalAltResult>> {
+        let todo = fetch_todo(todo_id(id)?).map_err(script_error)?;
+        Ok(todo.map_or(Dynamic::UNIT, |todo| todo_map(&todo, Local::now().date_naive())))
+    });
+    engine.register_fn("add", |line: &str| -> Result<i64, Box<EvalAltResult>> {
+        let id = QuickAdd::parse(line, &Config::load()).save().map_err(script_error)?;
+        Ok(id as i64)
+    });
+    engine.register_fn("complete", |id: i64| -> Result<(), Box<EvalAltResult>> {
+        toggle_todo_completion(todo_id(id)?, true).map_err(script_error)
+    });
+    engine
+}
+
+fn open_todos(query: TodoQuery) -> Result<Array, Box<EvalAltResult>> {
+    let today = Local::now().date_naive();
+    let todos = fetch_todos(&query.completed(false)).map_err(script_error)?;
+    Ok(todos.iter().map(|todo| todo_map(todo, today)).collect())
+}
+
+fn todo_id(id: i64) -> Result<usize, Box<EvalAltResult>> {
+    usize::try_from(id).map_err(|_| format!("Invalid todo id {}", id).into())
+}
+
+/// The todo as scripts see it, with `days_left` until it is due from `today`.
+fn todo_map(todo: &Todo, today: NaiveDate) -> Dynamic {
+    let optional = |value: Option<String>| value.map_or(Dynamic::UNIT, Dynamic::from);
+    let mut map = Map::new();
+    map.insert("id".into(), Dynamic::from(todo.id.unwrap_or(0) as i64));
+    map.insert("list_id".into(), Dynamic::from(todo.list_id as i64));
+    map.insert("title".into(), Dynamic::from(todo.title.clone()));
+    map.insert("description".into(), optional(todo.description.clone()));
+    map.insert("due".into(), optional(todo.due_date.map(|d| d.to_string())));
+    let days_left = todo.due_date.map(|d| (d - today).num_days());
+    map.insert("days_left".into(), days_left.map_or(Dynamic::UNIT, Dynamic::from));
+    map.insert("priority".into(), Dynamic::from(todo.priority.to_string()));
+    map.insert("completed".into(), Dynamic::from(todo.completed));
+    let tags: Array = todo.tags().into_iter().map(Dynamic::from).collect();
+    map.insert("tags".into(), Dynamic::from(tags));
+    Dynamic::from(map)
+}
+
+fn script_error(e: impl Display) -> Box<EvalAltResult> {
+    e.to_string().into()
+}
+
+fn failed(name: &str, e: impl Display) -> Box<dyn Error> {
+    format!("Plugin {} failed: {}", name, e).into()
+}
+
+pub fn list() -> Result<(), Box<dyn Error>> {
+    let Ok(entries) = fs::read_dir(plugin_dir()) else {
+        println!("No plugins installed in {}", plugin_dir().display());
+        return Ok(());
+    };
+    let mut executables: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|e| e.path().is_file() && !is_script(&e.path()))
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    executables.sort();
+    let plugins = Plugins::load();
+    for plugin in &plugins.plugins {
+        println!("{}", plugin.name);
+        for (command, help) in plugin.commands() {
+            println!("  command {}: {}", command, help);
+        }
+        if plugin.scores_urgency() {
+            println!("  urgency");
+        }
+        for (key, label) in plugin.keys() {
+            println!("  key {}: {}", key, label);
+        }
+    }
+    executables.iter().for_each(|name| println!("{}", name));
+    plugins.errors.iter().for_each(|e| eprintln!("{}", e));
+    Ok(())
+}
+
+/// Runs a custom command: a script command of that name, or else the
+/// executable plugin, passing the terminal straight through.
+pub fn run(name: &str, args: &[String], workspace: &str) -> Result<(), Box<dyn Error>> {
+    let plugins = Plugins::load();
+    plugins.errors.iter().for_each(|e| eprintln!("{}", e));
+    if let Some(result) = plugins.run_command(name, args) {
+        result?.iter().for_each(|line| println!("{}", line));
+        return Ok(());
+    }
+    let status = command(name, workspace, &[])?.args(args).status()?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("Plugin {} failed: {}", name, status).into()),
+    }
+}
+
+/// Runs an executable plugin bound to a key in the TUI with the selected todo
+/// and list in `TODO_ID` and `TODO_LIST_ID`. Output is captured so it can't
+/// garble the screen.
+pub fn run_captured(name: &str, workspace: &str, env: &[(&str, String)]) -> Result<Output, Box<dyn Error>> {
+    Ok(command(name, workspace, env)?.output()?)
+}
+
+fn command(name: &str, workspace: &str, env: &[(&str, String)]) -> Result<Command, Box<dyn Error>> {
+    // Only plain file names, so a binding can't point outside the plugin dir.
+    if name.contains(std::path::is_separator) {
+        return Err(format!("Invalid plugin name: {}", name).into());
+    }
+    let path = plugin_dir().join(name);
+    if !path.is_file() {
+        return Err(format!("No plugin named {} in {}", name, plugin_dir().display()).into());
+    }
+    let mut command = Command::new(path);
+    command.env("TODO_BIN", env::current_exe()?);
+    // `todo` reads the workspace from here, so the plugin's calls change the
+    // same database. The path is for plugins that only read it.
+    command.env("TODO_WORKSPACE", workspace);
+    command.env("TODO_DB", database::get_path());
+    command.envs(env.iter().map(|(k, v)| (*k, v)));
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(title: &str) -> Todo {
+        Todo { id: Some(1), ..Todo::new(1, title.to_string()) }
+    }
+
+    #[test]
+    fn registers_commands_and_keys() {
+        let plugin = Plugin::compile(
+            "standup",
+            r#"
+                command("standup", "Print a greeting", |args| { print("hi " + args[0]); 42 });
+                key("x", "export", |todo| todo.title);
+            "#,
+        )
+        .unwrap();
+        assert_eq!(plugin.commands(), vec![("standup".to_string(), "Print a greeting".to_string())]);
+        let plugins = Plugins { plugins: vec![plugin], errors: vec![] };
+        let output = plugins.run_command("standup", &["there".to_string()]).unwrap().unwrap();
+        assert_eq!(output, vec!["hi there", "42"]);
+        assert!(plugins.run_command("other", &[]).is_none());
+        assert_eq!(plugins.keys(), vec![('x', "export".to_string())]);
+        assert_eq!(plugins.run_key('x', Some(&todo("Ship it"))).unwrap().unwrap(), "export: Ship it");
+    }
+
+    #[test]
+    fn chains_urgency_hooks() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let plugins = Plugins {
+            plugins: vec![
+                Plugin::compile("tags", r#"urgency(|todo, score| if "waiting" in todo.tags { score - 5 } else { score });"#)
+                    .unwrap(),
+                Plugin::compile("double", "urgency(|todo, score| score * 2);").unwrap(),
+            ],
+            errors: vec![],
+        };
+        assert_eq!(plugins.urgency(&todo("Call back #waiting"), today).unwrap(), -10.0);
+        assert_eq!(plugins.urgency(&todo("Call back"), today).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn rejects_runaway_scripts_and_long_keys() {
+        assert!(Plugin::compile("loop", "loop {}").is_err());
+        assert!(Plugin::compile("key", r#"key("xy", "two", |todo| ());"#).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use chrono::NaiveDate;
+use rusqlite::ToSql;
+
+/// The order todos are returned in. Ties are broken by id, i.e. creation order.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Order {
+    #[default]
+    Id,
+    /// Earliest due date first, todos without one last.
+    Due,
+    /// Most recently created first.
+    Created,
+    Title,
+}
+
+/// Describes which todos to fetch, see `database::fetch_todos`. Every
+/// condition is optional and they all have to match.
+///
+/// ```ignore
+/// let overdue = TodoQuery::new().completed(false).due_to(yesterday).order(Order::Due);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TodoQuery {
+    list_id: Option<usize>,
+    completed: Option<bool>,
+    due_from: Option<NaiveDate>,
+    due_to: Option<NaiveDate>,
+    tags: Vec<String>,
+    fields: Vec<(String, String)>,
+    text: Option<String>,
+    limit: Option<usize>,
+    order: Order,
+}
+
+impl TodoQuery {
+    pub fn new() -> TodoQuery {
+        TodoQuery::default()
+    }
+
+    pub fn list(mut self, list_id: usize) -> TodoQuery {
+        self.list_id = Some(list_id);
+        self
+    }
+
+    pub fn completed(mut self, completed: bool) -> TodoQuery {
+        self.completed = Some(completed);
+        self
+    }
+
+    /// Only todos due on or after `date`.
+    pub fn due_from(mut self, date: NaiveDate) -> TodoQuery {
+        self.due_from = Some(date);
+        self
+    }
+
+    /// Only todos due on or before `date`.
+    pub fn due_to(mut self, date: NaiveDate) -> TodoQuery {
+        self.due_to = Some(date);
+        self
+    }
+
+    /// Only todos with `#tag` in the title or description. Can be given
+    /// several times, in which case every tag has to be present.
+    pub fn tag(mut self, tag: &str) -> TodoQuery {
+        self.tags.push(tag.trim_start_matches('#').to_string());
+        self
+    }
+
+    /// Only todos whose custom field `key` is exactly `value`. Can be given
+    /// several times.
+    pub fn field(mut self, key: &str, value: &str) -> TodoQuery {
+        self.fields.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Only todos whose title or description contains `text`, ignoring case.
+    pub fn text(mut self, text: &str) -> TodoQuery {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> TodoQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> TodoQuery {
+        self.order = order;
+        self
+    }
+
+    /// The SQL selecting the matching rows of `todos`, with the values for
+    /// its numbered parameters.
+    pub fn to_sql(&self) -> (String, Vec<Box<dyn ToSql>>) {
+        let mut conditions = vec![];
+        let mut values: Vec<Box<dyn ToSql>> = vec![];
+        let mut bind = |value: Box<dyn ToSql>| {
+            values.push(value);
+            format!("?{}", values.len())
+        };
+
+        if let Some(list_id) = self.list_id {
+            conditions.push(format!("list_id = {}", bind(Box::new(list_id))));
+        }
+        if let Some(completed) = self.completed {
+            conditions.push(format!("completed = {}", bind(Box::new(completed))));
+        }
+        // Due dates may carry a time, compare the day only.
+        if let Some(date) = self.due_from {
+            conditions.push(format!("substr(due_date, 1, 10) >= {}", bind(Box::new(date.to_string()))));
+        }
+        if let Some(date) = self.due_to {
+            conditions.push(format!("substr(due_date, 1, 10) <= {}", bind(Box::new(date.to_string()))));
+        }
+        for tag in &self.tags {
+            // model::has_tag, registered by database::fetch_todos.
+            conditions.push(format!("has_tag(title, description, {})", bind(Box::new(tag.clone()))));
+        }
+        for (key, value) in &self.fields {
+            let key = bind(Box::new(key.clone()));
+            let value = bind(Box::new(value.clone()));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM fields WHERE fields.todo_id = todos.id AND key = {} AND value = {})",
+                key, value
+            ));
+        }
+        if let Some(text) = &self.text {
+            let pattern = format!("%{}%", escape_like(text));
+            let param = bind(Box::new(pattern));
+            conditions.push(format!(
+                "(title LIKE {0} ESCAPE '\\' OR description LIKE {0} ESCAPE '\\')",
+                param
+            ));
+        }
+
+        let mut sql = "SELECT * FROM todos".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(match self.order {
+            Order::Id => " ORDER BY id",
+            Order::Due => " ORDER BY due_date IS NULL, due_date, id",
+            Order::Created => " ORDER BY created_date DESC, id DESC",
+            Order::Title => " ORDER BY title COLLATE NOCASE, id",
+        });
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        (sql, values)
+    }
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    database::{
+        add_list, add_milestone, add_relation, add_todo, delete_list, delete_todo, escalate_overdue, expand_milestones,
+        fetch_milestones, set_milestone_collapsed, swap_milestones, toggle_todo_completion, update_list, update_todo,
+        SqlResult,
+    },
+    model::{Milestone, Relation, Todo, TodoList},
+    url::attach_url,
+};
+
+/// A change the TUI makes to the database.
+pub enum Operation {
+    AddTodo(Todo),
+    UpdateTodo(Todo),
+    ToggleTodo(usize, bool),
+    DeleteTodo(usize),
+    AddList(TodoList),
+    UpdateList(TodoList),
+    DeleteList(usize),
+    AddRelation(Relation),
+    /// Puts the todo under its list's milestone with this title, added if
+    /// the list has none yet.
+    SetMilestone(Todo, String),
+    CollapseMilestone(usize, bool),
+    /// Unfolds every milestone of the list.
+    ExpandMilestones(usize),
+    SwapMilestones(Milestone, Milestone),
+    /// Raises the priority of todos overdue by more than this many days.
+    Escalate(u64, NaiveDate),
+}
+
+impl Operation {
+    fn apply(&self) -> SqlResult<()> {
+        match self {
+            Operation::AddTodo(todo) => add_todo(todo).map(|id| attach_url(id, todo)),
+            Operation::UpdateTodo(todo) => update_todo(todo),
+            Operation::ToggleTodo(id, completed) => toggle_todo_completion(*id, *completed),
+            Operation::DeleteTodo(id) => delete_todo(*id),
+            Operation::AddList(list) => add_list(list).map(|_| ()),
+            Operation::UpdateList(list) => update_list(list),
+            Operation::DeleteList(id) => delete_list(*id),
+            Operation::AddRelation(relation) => add_relation(relation),
+            Operation::SetMilestone(todo, title) => {
+                // Looked up first, so a retry doesn't add the milestone twice.
+                let existing = fetch_milestones(todo.list_id)?
+                    .into_iter()
+                    .find(|m| m.title.eq_ignore_ascii_case(title))
+                    .and_then(|m| m.id);
+                let milestone_id = match existing {
+                    Some(id) => id,
+                    None => add_milestone(todo.list_id, title)?,
+                };
+                update_todo(&Todo { milestone_id: Some(milestone_id), ..todo.clone() })
+            }
+            Operation::CollapseMilestone(id, collapsed) => set_milestone_collapsed(*id, *collapsed),
+            Operation::ExpandMilestones(list_id) => expand_milestones(*list_id),
+            Operation::SwapMilestones(a, b) => swap_milestones(a, b),
+            Operation::Escalate(days, today) => escalate_overdue(*days, *today).map(|_| ()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Operation::AddTodo(todo) => format!("adding {}", todo.title),
+            Operation::UpdateTodo(todo) => format!("changing {}", todo.title),
+            Operation::ToggleTodo(id, true) => format!("completing {}", id),
+            Operation::ToggleTodo(id, false) => format!("reopening {}", id),
+            Operation::DeleteTodo(id) => format!("deleting {}", id),
+            Operation::AddList(list) => format!("adding list {}", list.title),
+            Operation::UpdateList(list) => format!("changing list {}", list.title),
+            Operation::DeleteList(id) => format!("deleting list {}", id),
+            Operation::AddRelation(relation) => format!("linking {} to {}", relation.todo_id, relation.related_id),
+            Operation::SetMilestone(todo, title) => format!("moving {} to {}", todo.title, title),
+            Operation::CollapseMilestone(id, true) => format!("folding milestone {}", id),
+            Operation::CollapseMilestone(id, false) => format!("unfolding milestone {}", id),
+            Operation::ExpandMilestones(list_id) => format!("unfolding the milestones of list {}", list_id),
+            Operation::SwapMilestones(a, b) => format!("moving {} past {}", a.title, b.title),
+            Operation::Escalate(..) => "raising the priority of overdue todos".to_string(),
+        }
+    }
+
+    /// The existing todo this changes.
+    fn todo_id(&self) -> Option<usize> {
+        match self {
+            Operation::UpdateTodo(todo) | Operation::SetMilestone(todo, _) => todo.id,
+            Operation::ToggleTodo(id, _) | Operation::DeleteTodo(id) => Some(*id),
+            Operation::AddRelation(relation) => Some(relation.todo_id),
+            _ => None,
+        }
+    }
+}
+
+/// Attempts before an operation is given up on, about five minutes in.
+const MAX_ATTEMPTS: u32 = 10;
+
+/// Waits twice as long after every failed attempt, up to a minute.
+fn backoff(attempts: u32) -> Duration {
+    Duration::from_secs(1 << attempts.min(6)).min(Duration::from_secs(60))
+}
+
+struct Pending {
+    operation: Operation,
+    attempts: u32,
+    retry_at: Instant,
+}
+
+/// Writes that failed, e.g. on a locked database, retried in order with
+/// backoff instead of being dropped.
+#[derive(Default)]
+pub struct Queue {
+    pending: VecDeque<Pending>,
+}
+
+impl Queue {
+    /// Applies the operation, or queues it when it fails or earlier ones are
+    /// still waiting, as it may depend on them.
+    pub fn submit(&mut self, operation: Operation) {
+        if self.pending.is_empty() && operation.apply().is_ok() {
+            return;
+        }
+        let attempts = if self.pending.is_empty() { 1 } else { 0 };
+        self.pending.push_back(Pending {
+            operation,
+            attempts,
+            retry_at: Instant::now() + backoff(attempts),
+        });
+    }
+
+    /// Retries the waiting operations that are due, returning a message
+    /// when one was saved or given up on.
+    pub fn retry(&mut self) -> Option<String> {
+        let pending = self.pending.front_mut()?;
+        if pending.retry_at > Instant::now() {
+            return None;
+        }
+        match pending.operation.apply() {
+            Ok(()) => {
+                let saved = pending.operation.describe();
+                self.pending.pop_front();
+                Some(format!("Saved after retrying: {}", saved))
+            }
+            Err(e) if pending.attempts + 1 >= MAX_ATTEMPTS => {
+                let message = format!("Gave up {}: {}", pending.operation.describe(), e);
+                self.pending.pop_front();
+                Some(message)
+            }
+            Err(_) => {
+                pending.attempts += 1;
+                pending.retry_at = Instant::now() + backoff(pending.attempts);
+                None
+            }
+        }
+    }
+
+    /// Tries every waiting operation once more, right away, stopping at the
+    /// first that still fails. Returns the ones left over.
+    pub fn flush(&mut self) -> Vec<String> {
+        while let Some(pending) = self.pending.front() {
+            if pending.operation.apply().is_err() {
+                break;
+            }
+            self.pending.pop_front();
+        }
+        self.pending.iter().map(|pending| pending.operation.describe()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_pending(&self, todo_id: usize) -> bool {
+        self.pending.iter().any(|pending| pending.operation.todo_id() == Some(todo_id))
+    }
+}
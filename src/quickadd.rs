@@ -0,0 +1,109 @@
+use std::error::Error;
+
+use chrono::{Local, NaiveDate};
+
+use crate::{
+    config::Config,
+    database::add_todo,
+    import::find_or_create_list,
+    model::{TimeSlot, Todo},
+};
+
+/// List used when a quick-add line does not name one.
+pub const DEFAULT_LIST: &str = "Inbox";
+
+/// A todo described on a single line, e.g.
+/// `Call the dentist @Home due:+2 at:morning`.
+///
+/// * `@List` puts the todo in that list, created if missing
+/// * `due:YYYY-MM-DD`, `due:+N`, `due:today` or `due:tomorrow` sets the due date
+/// * `at:morning|afternoon|evening|HH:MM` sets the time of day
+///
+/// Everything else makes up the title.
+#[derive(Debug)]
+pub struct QuickAdd {
+    pub title: String,
+    pub list: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    pub slot: Option<TimeSlot>,
+    /// The description template of the list, see `Config::description_template`.
+    pub description: Option<String>,
+}
+
+impl QuickAdd {
+    pub fn parse(line: &str, config: &Config) -> QuickAdd {
+        let today = Local::now().date_naive();
+        let mut title = vec![];
+        let mut list = None;
+        let mut due_date = None;
+        let mut slot = None;
+        for word in line.split_whitespace() {
+            if let Some(name) = word.strip_prefix('@').filter(|n| !n.is_empty()) {
+                list = Some(name.to_string());
+            } else if let Some(date) = word.strip_prefix("due:").and_then(|d| parse_due(d, today, config).ok()) {
+                due_date = Some(date);
+            } else if let Some(parsed) = word.strip_prefix("at:").and_then(TimeSlot::parse) {
+                slot = Some(parsed);
+            } else {
+                title.push(word);
+            }
+        }
+        let description = config.description_template(list.as_deref().unwrap_or(DEFAULT_LIST)).map(String::from);
+        QuickAdd {
+            title: title.join(" "),
+            list,
+            description,
+            due_date,
+            slot,
+        }
+    }
+
+    /// Puts the todo in `list` unless the line named one.
+    pub fn or_list(mut self, list: &str, config: &Config) -> QuickAdd {
+        if self.list.is_none() {
+            self.description = config.description_template(list).map(String::from);
+            self.list = Some(list.to_string());
+        }
+        self
+    }
+
+    /// The title of the list the todo is saved to.
+    pub fn list_title(&self) -> &str {
+        self.list.as_deref().unwrap_or(DEFAULT_LIST)
+    }
+
+    /// Saves the todo, creating its list if needed, and returns the new id.
+    pub fn save(self) -> Result<usize, Box<dyn Error>> {
+        if self.title.is_empty() {
+            return Err("A todo needs a title".into());
+        }
+        let list_id = find_or_create_list(self.list_title())?;
+        let todo = Todo {
+            description: self.description,
+            due_date: self.due_date,
+            created_date: Some(Local::now().date_naive()),
+            slot: self.slot,
+            ..Todo::new(list_id, self.title)
+        };
+        Ok(add_todo(&todo)?)
+    }
+}
+
+/// Reads `YYYY-MM-DD`, `today`, `tomorrow` or a number of days from `today`,
+/// with or without a leading `+`.
+pub fn parse_due(value: &str, today: NaiveDate, config: &Config) -> Result<NaiveDate, String> {
+    let value = value.trim();
+    let days = value.strip_prefix('+').unwrap_or(value);
+    let days: u64 = match value {
+        "today" => return Ok(today),
+        "tomorrow" => 1,
+        _ if !days.is_empty() && days.chars().all(|c| c.is_ascii_digit()) => {
+            days.parse().map_err(|_| format!("{} days is too far ahead", days))?
+        }
+        _ => {
+            return NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|e| format!("Not a date ({}), use YYYY-MM-DD, +days, today or tomorrow", e))
+        }
+    };
+    config.due_in(today, days).ok_or_else(|| format!("{} days is too far ahead", days))
+}
@@ -0,0 +1,103 @@
+use std::{
+    io::{self, Stdout},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use ratatui::{
+    buffer::Buffer,
+    prelude::CrosstermBackend,
+    style::{Color, Modifier},
+    Frame, Terminal,
+};
+
+/// Set once at startup from `Config::reduced_rendering`.
+static REDUCED: AtomicBool = AtomicBool::new(false);
+
+/// Set once at startup from `Config::no_color` and `Config::plain`.
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+static NO_MODIFIERS: AtomicBool = AtomicBool::new(false);
+
+/// Set once at startup from `Config::reduced_motion`.
+static REDUCED_MOTION: AtomicBool = AtomicBool::new(false);
+
+/// Switches to reduced rendering, meant for high latency connections like
+/// ssh. The screen is only redrawn after input, colors are dropped and box
+/// drawing characters are replaced by plain ASCII, which makes every update
+/// a fraction of the size.
+pub fn set_reduced(reduced: bool) {
+    REDUCED.store(reduced, Ordering::Relaxed);
+}
+
+pub fn reduced() -> bool {
+    REDUCED.load(Ordering::Relaxed)
+}
+
+/// Drops colors, and with `no_modifiers` bold, italic and the like too, from
+/// every frame. Whatever they conveyed has to be shown as text as well, see
+/// `colorless`.
+pub fn set_plain(no_color: bool, no_modifiers: bool) {
+    NO_COLOR.store(no_color, Ordering::Relaxed);
+    NO_MODIFIERS.store(no_modifiers, Ordering::Relaxed);
+}
+
+/// Whether colors are dropped, by `set_plain` or reduced rendering.
+pub fn colorless() -> bool {
+    reduced() || NO_COLOR.load(Ordering::Relaxed)
+}
+
+/// Only redraws the screen after something changed, instead of every time
+/// input is polled.
+pub fn set_reduced_motion(reduced_motion: bool) {
+    REDUCED_MOTION.store(reduced_motion, Ordering::Relaxed);
+}
+
+pub fn on_change_only() -> bool {
+    reduced() || REDUCED_MOTION.load(Ordering::Relaxed)
+}
+
+/// Like `Terminal::draw`, but simplifies the frame before it is written when
+/// rendering is reduced or plain.
+pub fn draw<F>(terminal: &mut Terminal<CrosstermBackend<Stdout>>, f: F) -> io::Result<()>
+where
+    F: FnOnce(&mut Frame<CrosstermBackend<Stdout>>),
+{
+    if !colorless() && !NO_MODIFIERS.load(Ordering::Relaxed) {
+        return terminal.draw(f).map(|_| ());
+    }
+
+    terminal.autoresize()?;
+    let mut frame = terminal.get_frame();
+    f(&mut frame);
+    simplify(terminal.current_buffer_mut());
+    terminal.flush()?;
+    terminal.hide_cursor()?;
+    terminal.swap_buffers();
+    io::Write::flush(terminal.backend_mut())
+}
+
+fn simplify(buffer: &mut Buffer) {
+    let (reduced, colorless, no_modifiers) = (reduced(), colorless(), NO_MODIFIERS.load(Ordering::Relaxed));
+    for cell in buffer.content.iter_mut() {
+        if colorless {
+            cell.fg = Color::Reset;
+            cell.bg = Color::Reset;
+        }
+        if no_modifiers {
+            cell.modifier = Modifier::empty();
+        } else if reduced {
+            // Keep what marks the selection, lists highlight it in italic,
+            // it costs little.
+            cell.modifier &= Modifier::BOLD | Modifier::ITALIC | Modifier::REVERSED;
+        }
+        if !reduced {
+            continue;
+        }
+        let ascii = match cell.symbol.as_str() {
+            "─" | "━" | "═" => "-",
+            "│" | "┃" | "║" => "|",
+            "┌" | "┐" | "└" | "┘" | "╭" | "╮" | "╰" | "╯" | "├" | "┤" | "┬" | "┴" | "┼" | "╔" | "╗" | "╚" | "╝" => "+",
+            _ => continue,
+        };
+        cell.set_symbol(ascii);
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::model::{Priority, Todo};
+
+/// What a color rule matches on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    /// `tag:name`, a `#name` in the title or description
+    Tag(String),
+    /// `list:Name`
+    List(String),
+    /// `due:<Nd`, incomplete and due within N days
+    DueWithin(i64),
+    /// `overdue`, incomplete and due today or earlier
+    Overdue,
+    /// `completed`
+    Completed,
+    /// `priority:high`, at least the given priority
+    Priority(Priority),
+}
+
+/// A `predicate -> style` line from the config, e.g. `tag:urgent -> bold red`.
+#[derive(Debug, Clone)]
+pub struct ColorRule {
+    pub predicate: Predicate,
+    pub style: Style,
+}
+
+impl ColorRule {
+    pub fn parse(rule: &str) -> Option<ColorRule> {
+        let (predicate, style) = rule.split_once("->")?;
+        Some(ColorRule {
+            predicate: parse_predicate(predicate.trim())?,
+            style: parse_style(style.trim())?,
+        })
+    }
+
+    pub fn matches(&self, todo: &Todo, list_title: &str, today: NaiveDate) -> bool {
+        let days_left = todo.due_date.map(|d| (d - today).num_days());
+        match &self.predicate {
+            Predicate::Tag(tag) => todo.has_tag(tag),
+            Predicate::List(list) => list_title.eq_ignore_ascii_case(list),
+            Predicate::DueWithin(days) => !todo.completed && days_left.is_some_and(|left| left < *days),
+            Predicate::Overdue => !todo.completed && days_left.is_some_and(|left| left <= 0),
+            Predicate::Completed => todo.completed,
+            Predicate::Priority(priority) => todo.priority >= *priority,
+        }
+    }
+}
+
+/// Used when the config has no color rules.
+pub const DEFAULT_RULES: [&str; 2] = ["overdue -> red", "priority:urgent -> bold"];
+
+pub fn default_rules() -> Vec<ColorRule> {
+    DEFAULT_RULES.iter().filter_map(|rule| ColorRule::parse(rule)).collect()
+}
+
+/// The style of a todo: every matching rule is applied in order on top of
+/// the plain style, so later rules win.
+pub fn style_for(rules: &[ColorRule], todo: &Todo, list_title: &str, today: NaiveDate) -> Style {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(todo, list_title, today))
+        .fold(Style::default().fg(Color::White), |style, rule| style.patch(rule.style))
+}
+
+/// What the matching rules would have colored a todo for, written out for
+/// when colors are off. Tags and completion already show in the row.
+pub fn marker_for(rules: &[ColorRule], todo: &Todo, list_title: &str, today: NaiveDate) -> Option<&'static str> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(todo, list_title, today))
+        .find_map(|rule| match rule.predicate {
+            Predicate::Overdue => Some("overdue"),
+            Predicate::DueWithin(_) => Some("due soon"),
+            _ => None,
+        })
+}
+
+fn parse_predicate(predicate: &str) -> Option<Predicate> {
+    match predicate.split_once(':') {
+        Some(("tag", tag)) => Some(Predicate::Tag(tag.trim_start_matches('#').to_string())),
+        Some(("list", list)) => Some(Predicate::List(list.to_string())),
+        Some(("priority", priority)) => Priority::parse(priority).map(Predicate::Priority),
+        Some(("due", due)) => due
+            .strip_prefix('<')?
+            .strip_suffix('d')?
+            .parse()
+            .ok()
+            .map(Predicate::DueWithin),
+        None if predicate == "overdue" => Some(Predicate::Overdue),
+        None if predicate == "completed" => Some(Predicate::Completed),
+        _ => None,
+    }
+}
+
+/// Modifiers and a color, e.g. `bold red`. `on <color>` sets the background.
+fn parse_style(style: &str) -> Option<Style> {
+    let mut result = Style::default();
+    let mut words = style.split_whitespace();
+    while let Some(word) = words.next() {
+        result = match word {
+            "bold" => result.add_modifier(Modifier::BOLD),
+            "dim" => result.add_modifier(Modifier::DIM),
+            "italic" => result.add_modifier(Modifier::ITALIC),
+            "underlined" => result.add_modifier(Modifier::UNDERLINED),
+            "reversed" => result.add_modifier(Modifier::REVERSED),
+            "crossed" => result.add_modifier(Modifier::CROSSED_OUT),
+            "on" => result.bg(parse_color(words.next()?)?),
+            color => result.fg(parse_color(color)?),
+        };
+    }
+    Some(result)
+}
+
+pub fn parse_color(color: &str) -> Option<Color> {
+    Some(match color {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" => Color::Gray,
+        "darkgray" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    })
+}
@@ -0,0 +1,977 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{
+    database::TIMESTAMP_FORMAT,
+    model::{Priority, TimeSlot, Todo},
+};
+
+/// The text formats todos can be exported to and imported from. Every format
+/// keeps every field of a todo, so exporting and importing again gives back
+/// the same todos.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// An array of objects, one per todo
+    Json,
+    /// A header row followed by one row per todo
+    Csv,
+    /// One todo per line, fields the format has no place for are written
+    /// as key:value extensions
+    TodoTxt,
+    /// An iCalendar file with a VTODO per todo, for calendar and task apps
+    Ics,
+}
+
+pub fn serialize(format: Format, todos: &[Todo]) -> String {
+    match format {
+        Format::Json => json::write(todos),
+        Format::Csv => csv::write(todos),
+        Format::TodoTxt => todo_txt::write(todos),
+        Format::Ics => ics::write(todos),
+    }
+}
+
+pub fn deserialize(format: Format, text: &str) -> Result<Vec<Todo>, String> {
+    match format {
+        Format::Json => json::read(text),
+        Format::Csv => csv::read(text),
+        Format::TodoTxt => todo_txt::read(text),
+        Format::Ics => ics::read(text),
+    }
+}
+
+/// The JSON members of a todo as `serialize` writes them, for output that
+/// adds its own.
+pub fn json_members(todo: &Todo) -> Vec<(&'static str, String)> {
+    json::members(todo)
+}
+
+pub fn json_object(members: &[(&str, String)]) -> String {
+    json::object(members)
+}
+
+pub fn json_string(value: &str) -> String {
+    json::string(value)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("Invalid date: {}", value))
+}
+
+fn parse_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT).map_err(|_| format!("Invalid timestamp: {}", value))
+}
+
+fn parse_slot(value: &str) -> Result<TimeSlot, String> {
+    TimeSlot::parse(value).ok_or_else(|| format!("Invalid time slot: {}", value))
+}
+
+fn parse_priority(value: &str) -> Result<Priority, String> {
+    Priority::parse(value).ok_or_else(|| format!("Invalid priority: {}", value))
+}
+
+fn parse_id(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("Invalid id: {}", value))
+}
+
+fn empty_todo() -> Todo {
+    Todo::new(0, String::new())
+}
+
+mod json {
+    use super::*;
+
+    pub fn write(todos: &[Todo]) -> String {
+        let mut out = "[\n".to_string();
+        for (index, todo) in todos.iter().enumerate() {
+            out.push_str(&format!("  {}", object(&members(todo))));
+            out.push_str(if index + 1 < todos.len() { ",\n" } else { "\n" });
+        }
+        out.push_str("]\n");
+        out
+    }
+
+    pub fn members(todo: &Todo) -> Vec<(&'static str, String)> {
+        vec![
+            ("id", todo.id.map_or("null".to_string(), |id| id.to_string())),
+            ("list_id", todo.list_id.to_string()),
+            ("title", string(&todo.title)),
+            ("description", optional(todo.description.as_deref())),
+            ("due_date", optional(todo.due_date.map(|d| d.to_string()).as_deref())),
+            ("completed", todo.completed.to_string()),
+            ("completed_at", optional(todo.completed_at.map(|d| d.format(TIMESTAMP_FORMAT).to_string()).as_deref())),
+            ("created_date", optional(todo.created_date.map(|d| d.to_string()).as_deref())),
+            ("slot", optional(todo.slot.map(|s| s.to_string()).as_deref())),
+            ("priority", string(&todo.priority.to_string())),
+            ("milestone_id", todo.milestone_id.map_or("null".to_string(), |id| id.to_string())),
+        ]
+    }
+
+    /// Members are written as given, their values already encoded.
+    pub fn object(members: &[(&str, String)]) -> String {
+        let members: Vec<String> = members.iter().map(|(key, value)| format!("{}: {}", string(key), value)).collect();
+        format!("{{{}}}", members.join(", "))
+    }
+
+    fn optional(value: Option<&str>) -> String {
+        value.map_or("null".to_string(), string)
+    }
+
+    pub fn string(value: &str) -> String {
+        let mut out = "\"".to_string();
+        for c in value.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    enum Value {
+        Null,
+        Bool(bool),
+        Number(String),
+        String(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    /// Arrays and objects nested deeper than this are refused rather than
+    /// overflowing the stack.
+    const MAX_DEPTH: usize = 64;
+
+    pub fn read(text: &str) -> Result<Vec<Todo>, String> {
+        let mut parser = Parser { text, pos: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos < text.len() {
+            return Err(format!("Unexpected data after JSON at byte {}", parser.pos));
+        }
+        let Value::Array(items) = value else {
+            return Err("Expected a JSON array of todos".to_string());
+        };
+        items.into_iter().map(todo).collect()
+    }
+
+    fn todo(value: Value) -> Result<Todo, String> {
+        let Value::Object(fields) = value else {
+            return Err("Expected a JSON object for every todo".to_string());
+        };
+        let mut todo = empty_todo();
+        for (key, value) in fields {
+            match (key.as_str(), value) {
+                (_, Value::Null) => {}
+                ("id", Value::Number(n)) => todo.id = Some(parse_id(&n)?),
+                ("list_id", Value::Number(n)) => todo.list_id = parse_id(&n)?,
+                ("title", Value::String(s)) => todo.title = s,
+                ("description", Value::String(s)) => todo.description = Some(s),
+                ("due_date", Value::String(s)) => todo.due_date = Some(parse_date(&s)?),
+                ("completed", Value::Bool(b)) => todo.completed = b,
+                ("completed_at", Value::String(s)) => todo.completed_at = Some(parse_timestamp(&s)?),
+                ("created_date", Value::String(s)) => todo.created_date = Some(parse_date(&s)?),
+                ("slot", Value::String(s)) => todo.slot = Some(parse_slot(&s)?),
+                ("priority", Value::String(s)) => todo.priority = parse_priority(&s)?,
+                ("milestone_id", Value::Number(n)) => todo.milestone_id = Some(parse_id(&n)?),
+                (key, _) => return Err(format!("Unexpected value for {}", key)),
+            }
+        }
+        Ok(todo)
+    }
+
+    struct Parser<'a> {
+        text: &'a str,
+        pos: usize,
+        /// Arrays and objects the parser is inside of.
+        depth: usize,
+    }
+
+    impl Parser<'_> {
+        fn peek(&self) -> Option<char> {
+            self.text[self.pos..].chars().next()
+        }
+
+        fn next(&mut self) -> Option<char> {
+            let c = self.peek()?;
+            self.pos += c.len_utf8();
+            Some(c)
+        }
+
+        fn whitespace(&mut self) {
+            while self.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+                self.pos += 1;
+            }
+        }
+
+        fn expect(&mut self, expected: char) -> Result<(), String> {
+            match self.next() {
+                Some(c) if c == expected => Ok(()),
+                _ => Err(format!("Expected '{}' at byte {}", expected, self.pos)),
+            }
+        }
+
+        fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+            if self.text[self.pos..].starts_with(word) {
+                self.pos += word.len();
+                Ok(value)
+            } else {
+                Err(format!("Unexpected token at byte {}", self.pos))
+            }
+        }
+
+        fn value(&mut self) -> Result<Value, String> {
+            if self.depth == MAX_DEPTH {
+                return Err(format!("Nested too deeply at byte {}", self.pos));
+            }
+            self.depth += 1;
+            let value = self.nested_value();
+            self.depth -= 1;
+            value
+        }
+
+        fn nested_value(&mut self) -> Result<Value, String> {
+            self.whitespace();
+            match self.peek() {
+                Some('n') => self.literal("null", Value::Null),
+                Some('t') => self.literal("true", Value::Bool(true)),
+                Some('f') => self.literal("false", Value::Bool(false)),
+                Some('"') => Ok(Value::String(self.string()?)),
+                Some('[') => {
+                    self.pos += 1;
+                    let mut items = vec![];
+                    self.whitespace();
+                    if self.peek() == Some(']') {
+                        self.pos += 1;
+                        return Ok(Value::Array(items));
+                    }
+                    loop {
+                        items.push(self.value()?);
+                        self.whitespace();
+                        match self.next() {
+                            Some(',') => continue,
+                            Some(']') => return Ok(Value::Array(items)),
+                            _ => return Err(format!("Expected ',' or ']' at byte {}", self.pos)),
+                        }
+                    }
+                }
+                Some('{') => {
+                    self.pos += 1;
+                    let mut fields = vec![];
+                    self.whitespace();
+                    if self.peek() == Some('}') {
+                        self.pos += 1;
+                        return Ok(Value::Object(fields));
+                    }
+                    loop {
+                        self.whitespace();
+                        let key = self.string()?;
+                        self.whitespace();
+                        self.expect(':')?;
+                        fields.push((key, self.value()?));
+                        self.whitespace();
+                        match self.next() {
+                            Some(',') => continue,
+                            Some('}') => return Ok(Value::Object(fields)),
+                            _ => return Err(format!("Expected ',' or '}}' at byte {}", self.pos)),
+                        }
+                    }
+                }
+                Some(c) if c == '-' || c.is_ascii_digit() => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' || c.is_ascii_digit()) {
+                        self.pos += 1;
+                    }
+                    Ok(Value::Number(self.text[start..self.pos].to_string()))
+                }
+                _ => Err(format!("Unexpected token at byte {}", self.pos)),
+            }
+        }
+
+        fn string(&mut self) -> Result<String, String> {
+            self.expect('"')?;
+            let mut out = String::new();
+            loop {
+                match self.next() {
+                    Some('"') => return Ok(out),
+                    Some('\\') => match self.next() {
+                        Some('"') => out.push('"'),
+                        Some('\\') => out.push('\\'),
+                        Some('/') => out.push('/'),
+                        Some('b') => out.push('\u{8}'),
+                        Some('f') => out.push('\u{c}'),
+                        Some('n') => out.push('\n'),
+                        Some('r') => out.push('\r'),
+                        Some('t') => out.push('\t'),
+                        Some('u') => {
+                            let high = self.hex()?;
+                            let code = if (0xd800..0xdc00).contains(&high) {
+                                self.expect('\\')?;
+                                self.expect('u')?;
+                                let low = self.hex()?;
+                                if !(0xdc00..=0xdfff).contains(&low) {
+                                    return Err(format!("Invalid surrogate pair before byte {}", self.pos));
+                                }
+                                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                            } else {
+                                high
+                            };
+                            out.push(char::from_u32(code).ok_or_else(|| format!("Invalid escape before byte {}", self.pos))?);
+                        }
+                        _ => return Err(format!("Invalid escape before byte {}", self.pos)),
+                    },
+                    Some(c) => out.push(c),
+                    None => return Err("Unterminated string".to_string()),
+                }
+            }
+        }
+
+        fn hex(&mut self) -> Result<u32, String> {
+            let digits = self.text.get(self.pos..self.pos + 4).ok_or("Truncated \\u escape")?;
+            // from_str_radix alone would take a sign as well.
+            if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("Invalid \\u escape: {}", digits));
+            }
+            let code = u32::from_str_radix(digits, 16).map_err(|_| format!("Invalid \\u escape: {}", digits))?;
+            self.pos += 4;
+            Ok(code)
+        }
+    }
+}
+
+mod csv {
+    use super::*;
+
+    const COLUMNS: [&str; 11] = [
+        "id",
+        "list_id",
+        "title",
+        "description",
+        "due_date",
+        "completed",
+        "completed_at",
+        "created_date",
+        "slot",
+        "priority",
+        "milestone_id",
+    ];
+
+    /// Text fields are always quoted, so an empty quoted field is an empty
+    /// string while an empty unquoted one is a missing value.
+    pub fn write(todos: &[Todo]) -> String {
+        let mut out = COLUMNS.join(",");
+        out.push('\n');
+        for todo in todos {
+            let fields = [
+                todo.id.map(|id| id.to_string()).unwrap_or_default(),
+                todo.list_id.to_string(),
+                quote(&todo.title),
+                todo.description.as_deref().map(quote).unwrap_or_default(),
+                todo.due_date.map(|d| d.to_string()).unwrap_or_default(),
+                todo.completed.to_string(),
+                todo.completed_at.map(|d| d.format(TIMESTAMP_FORMAT).to_string()).unwrap_or_default(),
+                todo.created_date.map(|d| d.to_string()).unwrap_or_default(),
+                todo.slot.map(|s| s.to_string()).unwrap_or_default(),
+                todo.priority.to_string(),
+                todo.milestone_id.map(|id| id.to_string()).unwrap_or_default(),
+            ];
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn quote(value: &str) -> String {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    }
+
+    struct Field {
+        value: String,
+        quoted: bool,
+    }
+
+    pub fn read(text: &str) -> Result<Vec<Todo>, String> {
+        let mut records = records(text)?.into_iter();
+        let Some(header) = records.next() else {
+            return Ok(vec![]);
+        };
+        let columns: Vec<String> = header.into_iter().map(|f| f.value).collect();
+        if !columns.iter().any(|c| c == "title") {
+            return Err("CSV header has no title column".to_string());
+        }
+
+        let mut todos = vec![];
+        for record in records {
+            let mut todo = empty_todo();
+            for (column, field) in columns.iter().zip(record) {
+                let value = field.value;
+                if value.is_empty() && !field.quoted {
+                    continue;
+                }
+                match column.as_str() {
+                    "id" => todo.id = Some(parse_id(&value)?),
+                    "list_id" => todo.list_id = parse_id(&value)?,
+                    "title" => todo.title = value,
+                    "description" => todo.description = Some(value),
+                    "due_date" => todo.due_date = Some(parse_date(&value)?),
+                    "completed" => todo.completed = value == "true",
+                    "completed_at" => todo.completed_at = Some(parse_timestamp(&value)?),
+                    "created_date" => todo.created_date = Some(parse_date(&value)?),
+                    "slot" => todo.slot = Some(parse_slot(&value)?),
+                    "priority" => todo.priority = parse_priority(&value)?,
+                    "milestone_id" => todo.milestone_id = Some(parse_id(&value)?),
+                    _ => {}
+                }
+            }
+            todos.push(todo);
+        }
+        Ok(todos)
+    }
+
+    /// Splits RFC 4180 text into records of fields. Blank lines are skipped.
+    fn records(text: &str) -> Result<Vec<Vec<Field>>, String> {
+        let mut records = vec![];
+        let mut record = vec![];
+        let mut field = Field { value: String::new(), quoted: false };
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if field.value.is_empty() && !field.quoted => {
+                    field.quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                field.value.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => field.value.push(c),
+                            None => return Err("Unterminated quoted field".to_string()),
+                        }
+                    }
+                }
+                ',' => record.push(std::mem::replace(&mut field, Field { value: String::new(), quoted: false })),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    record.push(std::mem::replace(&mut field, Field { value: String::new(), quoted: false }));
+                    let record = std::mem::take(&mut record);
+                    if !(record.len() == 1 && record[0].value.is_empty() && !record[0].quoted) {
+                        records.push(record);
+                    }
+                }
+                c => field.value.push(c),
+            }
+        }
+        if !field.value.is_empty() || field.quoted || !record.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+/// todo.txt, see https://github.com/todotxt/todo.txt. Completion and
+/// creation dates use the standard positions where the format allows them,
+/// everything else is written as `key:value`. Urgent and high priority are
+/// `(A)` and `(B)`, any other letter reads as normal. Characters that would change
+/// how a line is read, like line breaks or a title starting with a date, are
+/// percent encoded.
+mod todo_txt {
+    use super::*;
+
+    /// Like TIMESTAMP_FORMAT, but without a space.
+    const TIMESTAMP_EXTENSION: &str = "%Y-%m-%dT%H:%M:%S";
+
+    const KEYS: [&str; 9] = ["due", "created", "completed", "slot", "desc", "id", "list", "pri", "milestone"];
+
+    pub fn write(todos: &[Todo]) -> String {
+        let mut out = String::new();
+        for todo in todos {
+            let mut words = vec![];
+            let mut extensions = vec![];
+            if todo.completed {
+                words.push("x".to_string());
+            }
+            // Completed lines have no place for the priority.
+            match (todo.completed, priority_letter(todo.priority)) {
+                (false, Some(letter)) => words.push(format!("({})", letter)),
+                (true, Some(letter)) => extensions.push(format!("pri:{}", letter)),
+                (_, None) => {}
+            }
+            match (todo.completed, todo.completed_at, todo.created_date) {
+                (true, Some(completed), created) => {
+                    words.push(completed.date().to_string());
+                    words.extend(created.map(|d| d.to_string()));
+                }
+                (true, None, Some(created)) => extensions.push(format!("created:{}", created)),
+                (false, _, created) => words.extend(created.map(|d| d.to_string())),
+                (true, None, None) => {}
+            }
+            // The format only has a place for the completion date, and only
+            // on completed todos.
+            if let Some(completed) = todo.completed_at.filter(|d| !todo.completed || d.time() != NaiveTime::MIN) {
+                extensions.push(format!("completed:{}", completed.format(TIMESTAMP_EXTENSION)));
+            }
+            words.extend(title_words(&todo.title));
+            extensions.extend(todo.due_date.map(|d| format!("due:{}", d)));
+            extensions.extend(todo.slot.map(|s| format!("slot:{}", s)));
+            extensions.extend(todo.description.as_deref().map(|d| format!("desc:{}", encode(d, char::is_whitespace))));
+            extensions.extend(todo.id.map(|id| format!("id:{}", id)));
+            extensions.extend(todo.milestone_id.map(|id| format!("milestone:{}", id)));
+            // Always present, so that no line is empty.
+            extensions.push(format!("list:{}", todo.list_id));
+
+            words.extend(extensions);
+            out.push_str(&words.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn title_words(title: &str) -> Vec<String> {
+        let chars: Vec<char> = title.chars().collect();
+        // A single space between two words is kept, any other whitespace is
+        // encoded so that splitting the line on spaces gives the title back.
+        let separator = |index: usize| {
+            chars[index] == ' '
+                && index > 0
+                && !chars[index - 1].is_whitespace()
+                && chars.get(index + 1).is_some_and(|c| !c.is_whitespace())
+        };
+        let encoded: String = chars
+            .iter()
+            .enumerate()
+            .map(|(index, c)| match separator(index) {
+                true => " ".to_string(),
+                false => encode(&c.to_string(), char::is_whitespace),
+            })
+            .collect();
+
+        encoded
+            .split(' ')
+            .filter(|word| !word.is_empty())
+            .enumerate()
+            .map(|(index, word)| {
+                if index == 0 && (word == "x" || is_date(word) || is_priority(word)) {
+                    return format!("%{:02X}{}", word.as_bytes()[0], &word[1..]);
+                }
+                match word.split_once(':') {
+                    Some((key, rest)) if KEYS.contains(&key) => format!("{}%3A{}", key, rest),
+                    _ => word.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn read(text: &str) -> Result<Vec<Todo>, String> {
+        let mut todos = vec![];
+        for line in text.lines() {
+            let mut words = line.split(' ').filter(|word| !word.is_empty()).peekable();
+            if words.peek().is_none() {
+                continue;
+            }
+            let mut todo = empty_todo();
+            if words.peek() == Some(&"x") {
+                words.next();
+                todo.completed = true;
+                if let Some(date) = words.next_if(|word| is_date(word)) {
+                    todo.completed_at = Some(parse_date(date)?.and_time(NaiveTime::MIN));
+                }
+            }
+            if let Some(word) = words.next_if(|word| is_priority(word)) {
+                todo.priority = priority_from_letter(word.as_bytes()[1]);
+            }
+            if let Some(date) = words.next_if(|word| is_date(word)) {
+                todo.created_date = Some(parse_date(date)?);
+            }
+
+            let mut title = vec![];
+            for word in words {
+                match word.split_once(':') {
+                    Some(("due", value)) => todo.due_date = Some(parse_date(value)?),
+                    Some(("created", value)) => todo.created_date = Some(parse_date(value)?),
+                    Some(("completed", value)) => {
+                        todo.completed_at = Some(
+                            NaiveDateTime::parse_from_str(value, TIMESTAMP_EXTENSION)
+                                .map_err(|_| format!("Invalid timestamp: {}", value))?,
+                        )
+                    }
+                    Some(("slot", value)) => todo.slot = Some(parse_slot(value)?),
+                    Some(("desc", value)) => todo.description = Some(decode(value)?),
+                    Some(("id", value)) => todo.id = Some(parse_id(value)?),
+                    Some(("list", value)) => todo.list_id = parse_id(value)?,
+                    Some(("milestone", value)) => todo.milestone_id = Some(parse_id(value)?),
+                    Some(("pri", value)) if value.len() == 1 => todo.priority = priority_from_letter(value.as_bytes()[0]),
+                    _ => title.push(word),
+                }
+            }
+            todo.title = decode(&title.join(" "))?;
+            todos.push(todo);
+        }
+        Ok(todos)
+    }
+
+    fn is_priority(word: &str) -> bool {
+        let bytes = word.as_bytes();
+        bytes.len() == 3 && bytes[0] == b'(' && bytes[1].is_ascii_uppercase() && bytes[2] == b')'
+    }
+
+    fn priority_letter(priority: Priority) -> Option<char> {
+        match priority {
+            Priority::Normal => None,
+            Priority::High => Some('B'),
+            Priority::Urgent => Some('A'),
+        }
+    }
+
+    fn priority_from_letter(letter: u8) -> Priority {
+        match letter {
+            b'A' => Priority::Urgent,
+            b'B' => Priority::High,
+            _ => Priority::Normal,
+        }
+    }
+
+    fn is_date(word: &str) -> bool {
+        word.len() == 10 && NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok()
+    }
+
+    /// Percent encodes `%` and every character `special` picks.
+    fn encode(text: &str, special: impl Fn(char) -> bool) -> String {
+        let mut out = String::new();
+        for c in text.chars() {
+            if c == '%' || special(c) {
+                let mut buffer = [0; 4];
+                for byte in c.encode_utf8(&mut buffer).bytes() {
+                    out.push_str(&format!("%{:02X}", byte));
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// Reverses `encode`. A `%` not followed by two hex digits is kept as is,
+    /// so hand written files can use it freely.
+    fn decode(text: &str) -> Result<String, String> {
+        let bytes = text.as_bytes();
+        let mut out = vec![];
+        let mut index = 0;
+        while index < bytes.len() {
+            let byte = bytes[index];
+            let hex = bytes.get(index + 1..index + 3).and_then(|h| std::str::from_utf8(h).ok());
+            match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(decoded) if byte == b'%' => {
+                    out.push(decoded);
+                    index += 3;
+                }
+                _ => {
+                    out.push(byte);
+                    index += 1;
+                }
+            }
+        }
+        String::from_utf8(out).map_err(|_| format!("Invalid percent encoding in {}", text))
+    }
+}
+
+/// iCalendar, see RFC 5545. Every todo is a VTODO with the standard
+/// properties where there is one, its due and creation dates as dates and
+/// its completion as a floating local time. Urgent and high priority are 1
+/// and 3, normal has none. The rest, and the creation date, which RFC 5545
+/// only has as a timestamp, are `X-TODO-*` properties. Text escapes `\r` as
+/// well as the standard characters, so titles come back exactly.
+mod ics {
+    use super::*;
+
+    const DATE: &str = "%Y%m%d";
+    const DATE_TIME: &str = "%Y%m%dT%H%M%S";
+
+    pub fn write(todos: &[Todo]) -> String {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//todo-tui//EN".to_string(),
+        ];
+        for (index, todo) in todos.iter().enumerate() {
+            lines.push("BEGIN:VTODO".to_string());
+            let uid = todo.id.map_or_else(|| format!("new-{}", index), |id| id.to_string());
+            lines.push(format!("UID:todo-{}@todo-tui", uid));
+            lines.push(format!("SUMMARY:{}", escape(&todo.title)));
+            lines.extend(todo.description.as_deref().map(|d| format!("DESCRIPTION:{}", escape(d))));
+            lines.extend(todo.due_date.map(|d| format!("DUE;VALUE=DATE:{}", d.format(DATE))));
+            lines.push(format!("STATUS:{}", if todo.completed { "COMPLETED" } else { "NEEDS-ACTION" }));
+            lines.extend(todo.completed_at.map(|d| format!("COMPLETED:{}", d.format(DATE_TIME))));
+            match todo.priority {
+                Priority::Urgent => lines.push("PRIORITY:1".to_string()),
+                Priority::High => lines.push("PRIORITY:3".to_string()),
+                Priority::Normal => {}
+            }
+            lines.extend(todo.created_date.map(|d| format!("X-TODO-CREATED;VALUE=DATE:{}", d.format(DATE))));
+            lines.extend(todo.slot.map(|s| format!("X-TODO-SLOT:{}", s)));
+            lines.extend(todo.id.map(|id| format!("X-TODO-ID:{}", id)));
+            lines.push(format!("X-TODO-LIST-ID:{}", todo.list_id));
+            lines.extend(todo.milestone_id.map(|id| format!("X-TODO-MILESTONE-ID:{}", id)));
+            lines.push("END:VTODO".to_string());
+        }
+        lines.push("END:VCALENDAR".to_string());
+        lines.iter().map(|line| fold(line)).collect()
+    }
+
+    /// Breaks lines longer than 75 bytes, between characters, with a line
+    /// break followed by a space.
+    fn fold(line: &str) -> String {
+        let mut out = String::new();
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                out.push_str("\r\n ");
+                width = 1;
+            }
+            out.push(c);
+            width += c.len_utf8();
+        }
+        out.push_str("\r\n");
+        out
+    }
+
+    fn escape(text: &str) -> String {
+        let mut out = String::new();
+        for c in text.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                ';' => out.push_str("\\;"),
+                ',' => out.push_str("\\,"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// Reverses `escape`. Unknown escapes are kept as they are.
+    fn unescape(text: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n' | 'N') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some(c @ ('\\' | ';' | ',')) => out.push(c),
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            }
+        }
+        out
+    }
+
+    pub fn read(text: &str) -> Result<Vec<Todo>, String> {
+        // Lines starting with a space or tab continue the one before.
+        let mut lines: Vec<String> = vec![];
+        for line in text.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(rest), Some(last)) => last.push_str(rest),
+                _ if line.is_empty() => {}
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        let mut todos = vec![];
+        let mut todo: Option<Todo> = None;
+        for line in &lines {
+            let Some((name, value)) = line.split_once(':') else {
+                return Err(format!("Invalid iCalendar line: {}", line));
+            };
+            // Parameters like VALUE=DATE don't change how values are read.
+            let name = name.split(';').next().unwrap_or_default().to_ascii_uppercase();
+            match (name.as_str(), todo.as_mut()) {
+                ("BEGIN", None) if value.eq_ignore_ascii_case("VTODO") => todo = Some(empty_todo()),
+                ("END", Some(_)) if value.eq_ignore_ascii_case("VTODO") => todos.extend(todo.take()),
+                ("SUMMARY", Some(todo)) => todo.title = unescape(value),
+                ("DESCRIPTION", Some(todo)) => todo.description = Some(unescape(value)),
+                ("DUE", Some(todo)) => todo.due_date = Some(date(value)?),
+                ("STATUS", Some(todo)) => todo.completed = value.eq_ignore_ascii_case("COMPLETED"),
+                ("COMPLETED", Some(todo)) => todo.completed_at = Some(date_time(value)?),
+                ("PRIORITY", Some(todo)) => {
+                    todo.priority = match value.trim().parse::<u8>() {
+                        Ok(1..=2) => Priority::Urgent,
+                        Ok(3..=4) => Priority::High,
+                        Ok(_) => Priority::Normal,
+                        Err(_) => return Err(format!("Invalid priority: {}", value)),
+                    }
+                }
+                ("X-TODO-CREATED", Some(todo)) => todo.created_date = Some(date(value)?),
+                ("X-TODO-SLOT", Some(todo)) => todo.slot = Some(parse_slot(value)?),
+                ("X-TODO-ID", Some(todo)) => todo.id = Some(parse_id(value)?),
+                ("X-TODO-LIST-ID", Some(todo)) => todo.list_id = parse_id(value)?,
+                ("X-TODO-MILESTONE-ID", Some(todo)) => todo.milestone_id = Some(parse_id(value)?),
+                _ => {}
+            }
+        }
+        match todo {
+            Some(_) => Err("Unterminated VTODO".to_string()),
+            None => Ok(todos),
+        }
+    }
+
+    /// A date, or the date of a timestamp as other apps write due dates.
+    fn date(value: &str) -> Result<NaiveDate, String> {
+        let day = value.get(..8).unwrap_or(value);
+        NaiveDate::parse_from_str(day, DATE).map_err(|_| format!("Invalid date: {}", value))
+    }
+
+    /// A floating or UTC timestamp, read as local time.
+    fn date_time(value: &str) -> Result<NaiveDateTime, String> {
+        let value = value.strip_suffix('Z').unwrap_or(value);
+        NaiveDateTime::parse_from_str(value, DATE_TIME)
+            .or_else(|_| date(value).map(|d| d.and_time(NaiveTime::MIN)))
+            .map_err(|_| format!("Invalid timestamp: {}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{collection::vec, option, prelude::*, sample::select};
+
+    /// Pieces that trip up one format or another, so they turn up more often
+    /// than arbitrary text alone would have them.
+    const PIECES: [&str; 36] = [
+        "a", "Z", "7", " ", "  ", "\t", "\n", "\r\n", "\r", ",", ";", "\"", "'", "\\", "\\n", "%", "%41", ":",
+        "due:", "desc:x", "#tag", "@home", "+project", "x", "(A)", "2024-02-29", "é", "日本", "🎉", "\u{2028}",
+        "\u{a0}", "\u{1}", "/", "{}", "[]", "END:VTODO",
+    ];
+
+    fn text() -> impl Strategy<Value = String> {
+        prop_oneof![vec(select(&PIECES[..]), 0..8).prop_map(|pieces| pieces.concat()), any::<String>()]
+    }
+
+    fn date() -> impl Strategy<Value = NaiveDate> {
+        let edges = [(1, 1, 1), (9999, 12, 31), (2024, 2, 29), (1970, 1, 1), (1999, 12, 31), (2000, 1, 1)]
+            .map(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d).unwrap());
+        prop_oneof![
+            select(edges.to_vec()),
+            (1..=3_652_059).prop_map(|days| NaiveDate::from_num_days_from_ce_opt(days).unwrap()),
+        ]
+    }
+
+    fn timestamp() -> impl Strategy<Value = NaiveDateTime> {
+        let seconds = (0..86_400u32).prop_map(|s| NaiveTime::from_num_seconds_from_midnight_opt(s, 0).unwrap());
+        let time = prop_oneof![Just(NaiveTime::MIN), seconds];
+        (date(), time).prop_map(|(date, time)| date.and_time(time))
+    }
+
+    fn slot() -> impl Strategy<Value = TimeSlot> {
+        prop_oneof![
+            Just(TimeSlot::Morning),
+            Just(TimeSlot::Afternoon),
+            Just(TimeSlot::Evening),
+            (0..24u32, 0..60u32).prop_map(|(h, m)| TimeSlot::At(NaiveTime::from_hms_opt(h, m, 0).unwrap())),
+        ]
+    }
+
+    fn todo() -> impl Strategy<Value = Todo> {
+        let priority = select(vec![Priority::Normal, Priority::High, Priority::Urgent]);
+        (
+            (option::of(0..100_000usize), 0..1_000usize, text(), option::of(text())),
+            (option::of(date()), any::<bool>(), option::of(date()), option::of(slot())),
+            (option::of(timestamp()), priority, option::of(0..1_000usize)),
+        )
+            .prop_map(|(first, second, third)| {
+                let (id, list_id, title, description) = first;
+                let (due_date, completed, created_date, slot) = second;
+                let (completed_at, priority, milestone_id) = third;
+                Todo {
+                    id,
+                    list_id,
+                    title,
+                    description,
+                    due_date,
+                    completed,
+                    created_date,
+                    slot,
+                    completed_at,
+                    priority,
+                    milestone_id,
+                    dependencies: vec![],
+                }
+            })
+    }
+
+    fn roundtrip(format: Format, todos: &[Todo]) -> Result<(), TestCaseError> {
+        let text = serialize(format, todos);
+        let read = deserialize(format, &text);
+        prop_assert_eq!(read.as_deref(), Ok(todos), "{:?} roundtrip of:\n{}", format, text);
+        Ok(())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1_000))]
+
+        #[test]
+        fn json_roundtrip(todos in vec(todo(), 0..4)) {
+            roundtrip(Format::Json, &todos)?;
+        }
+
+        #[test]
+        fn csv_roundtrip(todos in vec(todo(), 0..4)) {
+            roundtrip(Format::Csv, &todos)?;
+        }
+
+        #[test]
+        fn todo_txt_roundtrip(todos in vec(todo(), 0..4)) {
+            roundtrip(Format::TodoTxt, &todos)?;
+        }
+
+        #[test]
+        fn ics_roundtrip(todos in vec(todo(), 0..4)) {
+            roundtrip(Format::Ics, &todos)?;
+        }
+    }
+
+    #[test]
+    fn reads_hand_written_todo_txt() {
+        let todos = deserialize(Format::TodoTxt, "x 2024-01-02 2024-01-01 Call mom @phone due:2024-01-05\n\n50% off\n").unwrap();
+        assert_eq!(todos.len(), 2);
+        assert!(todos[0].completed);
+        assert_eq!(todos[0].completed_at, NaiveDate::from_ymd_opt(2024, 1, 2).map(|d| d.and_time(NaiveTime::MIN)));
+        assert_eq!(todos[0].created_date, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(todos[0].due_date, NaiveDate::from_ymd_opt(2024, 1, 5));
+        assert_eq!(todos[0].title, "Call mom @phone");
+        assert_eq!(todos[1].title, "50% off");
+    }
+
+    #[test]
+    fn reads_json_with_unicode_escapes() {
+        let todos = deserialize(Format::Json, r#"[{"title": "caf\u00e9 \ud83c\udf89", "list_id": 1}]"#).unwrap();
+        assert_eq!(todos[0].title, "café 🎉");
+    }
+
+    #[test]
+    fn refuses_deeply_nested_json() {
+        let nested = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert!(deserialize(Format::Json, &nested).unwrap_err().contains("Nested too deeply"));
+    }
+
+    #[test]
+    fn refuses_invalid_unicode_escapes() {
+        for title in [r"\ud83c\u0041", r"\u+041", r"\u-041"] {
+            let json = format!(r#"[{{"title": "{}", "list_id": 1}}]"#, title);
+            assert!(deserialize(Format::Json, &json).is_err(), "{}", title);
+        }
+    }
+}
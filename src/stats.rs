@@ -0,0 +1,145 @@
+use chrono::NaiveDate;
+
+use crate::{
+    config::Config,
+    model::{Todo, TodoList},
+};
+
+/// How many days of history the pace is measured over.
+pub const WINDOW_DAYS: i64 = 28;
+
+pub enum Forecast {
+    /// Nothing left to do.
+    Clear,
+    /// Estimated number of days until the list is empty.
+    Days(i64),
+    /// Todos are being added at least as fast as they are completed.
+    NotClearing,
+}
+
+pub struct ListStats {
+    pub title: String,
+    pub open: usize,
+    pub completed: usize,
+    pub created: usize,
+    pub forecast: Forecast,
+}
+
+pub fn list_stats(list: &TodoList, todos: &[Todo], today: NaiveDate) -> ListStats {
+    let open = todos.iter().filter(|t| !t.completed).count();
+
+    // Lists younger than the window are measured over their own lifetime,
+    // otherwise a list created yesterday would look very slow.
+    let first_activity = todos
+        .iter()
+        .filter_map(|t| t.created_date.or(t.completed_at.map(|d| d.date())))
+        .min()
+        .unwrap_or(today);
+    let days = (today - first_activity).num_days().clamp(1, WINDOW_DAYS);
+    let since = today - chrono::Duration::days(days);
+
+    let completed = todos
+        .iter()
+        .filter(|t| t.completed && t.completed_at.is_some_and(|d| d.date() > since))
+        .count();
+    let created = todos
+        .iter()
+        .filter(|t| t.created_date.is_some_and(|d| d > since))
+        .count();
+
+    let net_per_day = (completed as f64 - created as f64) / days as f64;
+    let forecast = if open == 0 {
+        Forecast::Clear
+    } else if net_per_day <= 0.0 {
+        Forecast::NotClearing
+    } else {
+        Forecast::Days((open as f64 / net_per_day).ceil() as i64)
+    };
+
+    ListStats {
+        title: list.title.clone(),
+        open,
+        completed,
+        created,
+        forecast,
+    }
+}
+
+/// The age buckets of the aging report, each up to but not including its
+/// number of days. The last one has no end.
+pub const AGE_BUCKETS: [(&str, Option<i64>); 4] = [("0-7d", Some(7)), ("7-30d", Some(30)), ("30-90d", Some(90)), ("90d+", None)];
+
+pub struct Aging<'a> {
+    pub title: String,
+    /// Incomplete todos per bucket of `AGE_BUCKETS`.
+    pub buckets: [usize; 4],
+    /// Incomplete todos from before creation dates were recorded.
+    pub undated: usize,
+    /// The oldest incomplete todos with their age in days, oldest first.
+    pub oldest: Vec<(&'a Todo, i64)>,
+}
+
+/// Buckets the incomplete `todos` of `list` by how long ago they were
+/// created, keeping the `oldest` oldest.
+pub fn list_aging<'a>(list: &TodoList, todos: &'a [Todo], today: NaiveDate, oldest: usize) -> Aging<'a> {
+    let mut aged: Vec<(&Todo, i64)> = todos
+        .iter()
+        .filter(|t| !t.completed)
+        .filter_map(|t| t.created_date.map(|created| (t, (today - created).num_days().max(0))))
+        .collect();
+    let mut buckets = [0; 4];
+    for (_, age) in &aged {
+        let bucket = AGE_BUCKETS
+            .iter()
+            .position(|(_, end)| end.is_none_or(|end| *age < end))
+            .expect("Last bucket has no end");
+        buckets[bucket] += 1;
+    }
+    aged.sort_by_key(|(_, age)| std::cmp::Reverse(*age));
+    aged.truncate(oldest);
+
+    Aging {
+        title: list.title.clone(),
+        buckets,
+        undated: todos.iter().filter(|t| !t.completed && t.created_date.is_none()).count(),
+        oldest: aged,
+    }
+}
+
+/// A warning when the configured quotas are exceeded, given the number of
+/// todos and the open todos per list, see `database::count_todos`.
+pub fn quota_warning(config: &Config, total: usize, open: &[(String, usize)]) -> Option<String> {
+    let mut warnings = vec![];
+    if let Some(quota) = config.todo_quota.filter(|&quota| total > quota) {
+        warnings.push(format!(
+            "{} todos in the database, quota {}: `todo tidy` deletes long completed ones",
+            total, quota
+        ));
+    }
+    if let Some(quota) = config.list_quota {
+        let mut over: Vec<_> = open.iter().filter(|(_, count)| *count > quota).collect();
+        over.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        if let Some((title, count)) = over.first() {
+            let others = match over.len() {
+                1 => String::new(),
+                n => format!(" (and {} more)", n - 1),
+            };
+            warnings.push(format!(
+                "{} has {} open todos{}, quota {}: split it up or delete what won't get done",
+                title, count, others, quota
+            ));
+        }
+    }
+    (!warnings.is_empty()).then(|| warnings.join(". "))
+}
+
+impl Forecast {
+    pub fn describe(&self) -> String {
+        match self {
+            Forecast::Clear => "all done".to_string(),
+            Forecast::Days(1) => "at current pace, this list clears in ~1 day".to_string(),
+            Forecast::Days(days) => format!("at current pace, this list clears in ~{} days", days),
+            Forecast::NotClearing => "not clearing at current pace".to_string(),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::{collections::HashMap, error::Error};
+
+use crate::{
+    database::{fetch_lists, fetch_todos},
+    model::{tag_of, Todo},
+    query::TodoQuery,
+    quickadd::DEFAULT_LIST,
+};
+
+/// Words too common to tell lists apart.
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "from", "about", "into", "that", "this", "then", "than", "some", "get", "buy",
+    "call", "check", "make", "fix", "send", "new", "out",
+];
+
+/// Suggests a list for a todo from the words and tags of the todos already
+/// filed in each list. Every word of the new title counts towards the lists
+/// it was used in, in proportion to how often, so a word only ever used in
+/// one list points there with full weight. Tags count twice.
+#[derive(Default)]
+pub struct Classifier {
+    /// Per list title, how many of its todos use each word.
+    lists: Vec<(String, HashMap<String, usize>)>,
+    /// How many todos use each word, over all lists.
+    totals: HashMap<String, usize>,
+}
+
+/// The least score a suggestion needs, one word used only in that list.
+const MIN_SCORE: f64 = 1.0;
+
+impl Classifier {
+    /// Learns from every todo in the database, leaving out the Inbox, which
+    /// is where the todos are that nobody filed.
+    pub fn from_database() -> Result<Classifier, Box<dyn Error>> {
+        let lists = fetch_lists()?;
+        let todos = fetch_todos(&TodoQuery::new())?;
+        let examples = todos.iter().filter_map(|todo| {
+            let list = lists.iter().find(|l| l.id == Some(todo.list_id))?;
+            (list.title != DEFAULT_LIST).then_some((list.title.as_str(), todo))
+        });
+        Ok(Classifier::train(examples))
+    }
+
+    pub fn train<'a>(examples: impl IntoIterator<Item = (&'a str, &'a Todo)>) -> Classifier {
+        let mut classifier = Classifier::default();
+        for (list, todo) in examples {
+            let mut words = words(&todo.title);
+            words.extend(todo.tags().iter().map(|tag| format!("#{}", tag.to_lowercase())));
+            words.sort();
+            words.dedup();
+            let index = match classifier.lists.iter().position(|(title, _)| title == list) {
+                Some(index) => index,
+                None => {
+                    classifier.lists.push((list.to_string(), HashMap::new()));
+                    classifier.lists.len() - 1
+                }
+            };
+            for word in words {
+                *classifier.lists[index].1.entry(word.clone()).or_default() += 1;
+                *classifier.totals.entry(word).or_default() += 1;
+            }
+        }
+        classifier
+    }
+
+    /// The best matching list for a todo titled `title`, when one stands out.
+    pub fn suggest(&self, title: &str) -> Option<&str> {
+        let mut words = words(title);
+        words.sort();
+        words.dedup();
+        let mut scores: Vec<(f64, &str)> = self
+            .lists
+            .iter()
+            .map(|(list, counts)| {
+                let score = words
+                    .iter()
+                    .filter_map(|word| {
+                        let weight = if word.starts_with('#') { 2.0 } else { 1.0 };
+                        let share = *counts.get(word)? as f64 / self.totals[word] as f64;
+                        Some(weight * share)
+                    })
+                    .sum();
+                (score, list.as_str())
+            })
+            .collect();
+        scores.sort_by(|a, b| b.0.total_cmp(&a.0));
+        match scores[..] {
+            [(best, list), (second, _), ..] if best >= MIN_SCORE && best > second => Some(list),
+            [(best, list)] if best >= MIN_SCORE => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/// The lowercased words of `text` worth learning from, tags with their `#`.
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter_map(|word| match tag_of(word) {
+            Some(tag) => Some(format!("#{}", tag.to_lowercase())),
+            None => {
+                let word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+                (word.chars().count() >= 3 && !STOP_WORDS.contains(&word.as_str())).then_some(word)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(title: &str) -> Todo {
+        Todo::new(0, title.to_string())
+    }
+
+    #[test]
+    fn suggests_the_list_a_word_was_used_in() {
+        let todos = [
+            todo("Review the deploy script #ops"),
+            todo("Deploy the staging server"),
+            todo("Milk and eggs"),
+            todo("Bread"),
+        ];
+        let lists = ["Work", "Work", "Groceries", "Groceries"];
+        let classifier = Classifier::train(lists.into_iter().zip(&todos));
+        assert_eq!(classifier.suggest("Deploy the new release"), Some("Work"));
+        assert_eq!(classifier.suggest("Eggs, organic"), Some("Groceries"));
+        assert_eq!(classifier.suggest("Rotate the logs #ops"), Some("Work"));
+    }
+
+    #[test]
+    fn suggests_nothing_without_a_clear_match() {
+        let todos = [todo("Plan the trip"), todo("Plan the sprint")];
+        let classifier = Classifier::train(["Home", "Work"].into_iter().zip(&todos));
+        assert_eq!(classifier.suggest("Plan something"), None);
+        assert_eq!(classifier.suggest("Water the plants"), None);
+        assert_eq!(Classifier::default().suggest("Anything"), None);
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+
+use crate::model::{Todo, TodoList};
+
+/// Renders `{{ name }}` placeholders from `fields`. `\n` and `\t` in the
+/// template are turned into newlines and tabs so templates can be given on the
+/// command line.
+pub fn render(template: &str, fields: &HashMap<&str, String>) -> Result<String, String> {
+    let template = template.replace("\\n", "\n").replace("\\t", "\t");
+    let mut output = String::new();
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| "Unclosed {{ in template".to_string())?;
+        let name = rest[start + 2..start + end].trim();
+        match fields.get(name) {
+            Some(value) => output.push_str(value),
+            None => return Err(format!("Unknown template field: {}", name)),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// The fields a todo template can use.
+pub fn todo_fields(todo: &Todo, list: Option<&TodoList>) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("id", todo.id.map(|id| id.to_string()).unwrap_or_default()),
+        ("title", todo.title.clone()),
+        ("description", todo.description.clone().unwrap_or_default()),
+        ("due", todo.due_date.map(|d| d.to_string()).unwrap_or_default()),
+        ("slot", todo.slot.map(|s| s.to_string()).unwrap_or_default()),
+        ("created", todo.created_date.map(|d| d.to_string()).unwrap_or_default()),
+        ("completed", if todo.completed { "x" } else { " " }.to_string()),
+        ("completed_at", todo.completed_at.map(|d| d.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default()),
+        ("priority", todo.priority.to_string()),
+        ("list", list.map(|l| l.title.clone()).unwrap_or_default()),
+    ])
+}
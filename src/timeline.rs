@@ -0,0 +1,81 @@
+use chrono::{Datelike, Days, NaiveDate};
+
+use crate::model::Todo;
+
+/// Weeks with at least this many open todos due are marked as a crunch.
+pub const CRUNCH: usize = 3;
+
+/// A todo on the time axis. Columns are relative to `Timeline::first_day`.
+pub struct Bar {
+    pub title: String,
+    /// Where the span starts, from the todo's `start` field, if it has one
+    /// before the due date.
+    pub start: Option<usize>,
+    pub due: usize,
+    pub completed: bool,
+    pub overdue: bool,
+}
+
+pub struct Timeline {
+    pub days_per_column: u64,
+    pub columns: usize,
+    pub today: usize,
+    pub bars: Vec<Bar>,
+    /// The column each week starts at, with the date of its Monday.
+    pub weeks: Vec<(usize, NaiveDate)>,
+    /// Open todos due per week, in the order of `weeks`.
+    pub load: Vec<usize>,
+}
+
+/// Lays out the due dated `todos` on an axis `width` columns wide, from the
+/// Monday of the earliest week to the latest due date. `todos` pairs every
+/// todo with its start date.
+pub fn layout(todos: &[(Todo, Option<NaiveDate>)], today: NaiveDate, width: usize) -> Timeline {
+    let dated: Vec<_> = todos
+        .iter()
+        .filter_map(|(todo, start)| todo.due_date.map(|due| (todo, start.filter(|s| *s < due), due)))
+        .collect();
+    let earliest = dated.iter().map(|(_, start, due)| start.unwrap_or(*due)).min().unwrap_or(today).min(today);
+    let latest = dated.iter().map(|(_, _, due)| *due).max().unwrap_or(today).max(today);
+    let first_day = earliest - Days::new(earliest.weekday().num_days_from_monday() as u64);
+
+    let days = (latest - first_day).num_days() as u64 + 1;
+    let days_per_column = days.div_ceil(width.max(1) as u64).max(1);
+    let column = |date: NaiveDate| ((date - first_day).num_days() as u64 / days_per_column) as usize;
+
+    let mut bars: Vec<Bar> = dated
+        .iter()
+        .map(|(todo, start, due)| Bar {
+            title: todo.title.clone(),
+            start: start.map(column),
+            due: column(*due),
+            completed: todo.completed,
+            overdue: !todo.completed && *due < today,
+        })
+        .collect();
+    bars.sort_by_key(|bar| (bar.start.unwrap_or(bar.due), bar.due));
+
+    let mut weeks = vec![];
+    let mut load = vec![];
+    let mut monday = first_day;
+    while monday <= latest {
+        let sunday = monday + Days::new(6);
+        weeks.push((column(monday), monday));
+        load.push(
+            dated
+                .iter()
+                .filter(|(todo, _, due)| !todo.completed && monday <= *due && *due <= sunday)
+                .count(),
+        );
+        monday = monday + Days::new(7);
+    }
+
+    Timeline {
+        days_per_column,
+        columns: column(latest) + 1,
+        today: column(today),
+        bars,
+        weeks,
+        load,
+    }
+}
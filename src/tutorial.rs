@@ -0,0 +1,77 @@
+use crate::{database::fetch_todos, get_lists, query::TodoQuery, AppState, InputField, State};
+
+/// One step of `todo tutorial`: what to do, and how to tell that it was done.
+pub struct Step {
+    pub instruction: &'static str,
+    pub done: fn(&State) -> bool,
+}
+
+pub const STEPS: &[Step] = &[
+    Step {
+        instruction: "Press L to create a new list",
+        done: |state| matches!(state.state, AppState::CreateList(Some(InputField::Title), None)),
+    },
+    Step {
+        instruction: "Type a title, e.g. Groceries, and press enter",
+        done: |state| matches!(state.state, AppState::CreateList(None, None)) && !state.list_title.is_empty(),
+    },
+    Step {
+        instruction: "Press s to save the list",
+        done: |state| matches!(state.state, AppState::List(_)) && !get_lists().is_empty(),
+    },
+    Step {
+        instruction: "Press j to select the list",
+        done: |state| state.lists_cursor.selected().is_some(),
+    },
+    Step {
+        instruction: "Press l to move into the list",
+        done: |state| !state.selecting_list,
+    },
+    Step {
+        instruction: "Press N to add a todo",
+        done: |state| matches!(state.state, AppState::Create(Some(InputField::Title), None)),
+    },
+    Step {
+        instruction: "Type a title and press enter until all fields are filled or skipped",
+        done: |state| matches!(state.state, AppState::Create(None, None)) && !state.todo_title.is_empty(),
+    },
+    Step {
+        instruction: "Press s to save the todo",
+        done: |_| fetch_todos(&TodoQuery::new().limit(1)).is_ok_and(|todos| !todos.is_empty()),
+    },
+    Step {
+        instruction: "Press j to select the todo and space to complete it",
+        done: |_| fetch_todos(&TodoQuery::new().completed(true).limit(1)).is_ok_and(|todos| !todos.is_empty()),
+    },
+    Step {
+        instruction: "Press v to see the todo's details",
+        done: |state| matches!(state.state, AppState::List(Some(_))),
+    },
+    // Only reached with the details open, so back in the list means closed.
+    Step {
+        instruction: "Press v again to close them",
+        done: |state| matches!(state.state, AppState::List(None)),
+    },
+    Step {
+        instruction: "That's the basics! Press q to quit the tutorial",
+        done: |_| false,
+    },
+];
+
+/// The current instruction, e.g. `Tutorial 3/12: Press s to save the list`.
+pub fn hint(state: &State) -> Option<String> {
+    let step = state.tutorial?;
+    STEPS
+        .get(step)
+        .map(|s| format!("Tutorial {}/{}: {}", step + 1, STEPS.len(), s.instruction))
+}
+
+/// Moves to the next step once the current one is done. Called after every
+/// key press.
+pub fn advance(state: &mut State) {
+    if let Some(step) = state.tutorial {
+        if STEPS.get(step).is_some_and(|s| (s.done)(state)) {
+            state.tutorial = Some(step + 1);
+        }
+    }
+}
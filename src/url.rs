@@ -0,0 +1,70 @@
+#[cfg(feature = "url-titles")]
+use std::process::Command;
+
+#[cfg(feature = "url-titles")]
+use crate::database::set_todo_title;
+use crate::{database::add_attachment, model::Todo};
+
+/// The text if it is nothing but a single http(s) URL.
+pub fn as_url(text: &str) -> Option<&str> {
+    let text = text.trim();
+    let is_url = (text.starts_with("http://") || text.starts_with("https://"))
+        && !text.contains(char::is_whitespace);
+    is_url.then_some(text)
+}
+
+/// Keeps a todo created from a bare URL as an attachment and, with the
+/// `url-titles` feature, replaces the title with the page title in the background.
+pub fn attach_url(id: usize, todo: &Todo) {
+    let title_url = as_url(&todo.title);
+    let description_url = todo.description.as_deref().and_then(as_url);
+    let Some(url) = title_url.or(description_url) else {
+        return;
+    };
+    add_attachment(id, url).ok();
+
+    #[cfg(feature = "url-titles")]
+    if title_url.is_some() || todo.title.trim().is_empty() {
+        let url = url.to_string();
+        std::thread::spawn(move || {
+            if let Some(title) = fetch_title(&url) {
+                set_todo_title(id, &title).ok();
+            }
+        });
+    }
+}
+
+/// Fetches the `<title>` of a page with curl, like `list_template::read`,
+/// which brings TLS and redirects along. `None` when curl is missing or the
+/// page has no title.
+#[cfg(feature = "url-titles")]
+pub fn fetch_title(url: &str) -> Option<String> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--location", "--max-redirs", "5", "--max-time", "5"])
+        .args(["--max-filesize", "524288", "--user-agent", "todo-tui", url])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    extract_title(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(feature = "url-titles")]
+fn extract_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title>")?;
+    let title = html
+        .get(start..end)?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    (!title.is_empty()).then_some(title)
+}